@@ -0,0 +1,117 @@
+use std::f64::consts::PI;
+
+// unit that trigonometric functions take and inverse ones give
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Angle {
+    Radians,
+    Degrees,
+    Gradians,
+}
+
+// sines of the first quadrant in steps of 15 degrees, for the steps where they're known exactly
+fn quadrant_sine(step: i64) -> Option<f64> {
+    match step {
+        0 => Some(0.0),
+        2 => Some(0.5),
+        3 => Some(0.5f64.sqrt()),
+        4 => Some(0.75f64.sqrt()),
+        6 => Some(1.0),
+        _ => None,
+    }
+}
+
+// sine of a multiple of 15 degrees
+fn exact_sine(step: i64) -> Option<f64> {
+    match step.rem_euclid(24) {
+        step @ 0..=6 => quadrant_sine(step),
+        step @ 7..=12 => quadrant_sine(12 - step),
+        step @ 13..=18 => quadrant_sine(step - 12).map(|x| -x),
+        step => quadrant_sine(24 - step).map(|x| -x),
+    }
+}
+
+impl Angle {
+    pub fn parse(name: &str) -> Result<Angle, String> {
+        match name {
+            "rad" => Ok(Angle::Radians),
+            "deg" => Ok(Angle::Degrees),
+            "grad" => Ok(Angle::Gradians),
+            _ => Err(format!("Unknown angle unit '{}', expected rad, deg or grad", name)),
+        }
+    }
+
+    // size of the unit in radians
+    pub fn radians(&self) -> f64 {
+        match *self {
+            Angle::Radians => 1.0,
+            Angle::Degrees => PI / 180.0,
+            Angle::Gradians => PI / 200.0,
+        }
+    }
+
+    fn turn(&self) -> f64 {
+        match *self {
+            Angle::Radians => 2.0 * PI,
+            Angle::Degrees => 360.0,
+            Angle::Gradians => 400.0,
+        }
+    }
+
+    // how many steps of 15 degrees an angle is, if it's one of them give or take rounding. written so
+    // that NaN isn't one
+    fn steps(&self, x: f64) -> Option<i64> {
+        let step = self.turn() / 24.0;
+        let count = (x / step).round();
+        let nearest = count * step;
+        if !(count.abs() <= 1e15 && (x - nearest).abs() <= 2.0 * f64::EPSILON * nearest.abs()) {
+            return None;
+        }
+        Some(count as i64)
+    }
+
+    fn in_radians(self, x: f64) -> f64 {
+        match self {
+            Angle::Radians => x,
+            Angle::Degrees => x.to_radians(),
+            Angle::Gradians => x * self.radians(),
+        }
+    }
+
+    pub fn sin(&self, x: f64) -> f64 {
+        self.steps(x).and_then(exact_sine).unwrap_or_else(|| self.in_radians(x).sin())
+    }
+
+    pub fn cos(&self, x: f64) -> f64 {
+        self.steps(x).and_then(|step| exact_sine(step + 6)).unwrap_or_else(|| self.in_radians(x).cos())
+    }
+
+    // exactly infinite at the poles
+    pub fn tan(&self, x: f64) -> f64 {
+        match self.steps(x).and_then(|step| Some((exact_sine(step)?, exact_sine(step + 6)?))) {
+            Some((sine, cosine)) => sine / cosine,
+            None => self.in_radians(x).tan(),
+        }
+    }
+
+    // an angle given by an inverse function, landing exactly on the special angles
+    pub fn inverse(&self, x: f64) -> f64 {
+        let angle = match *self {
+            Angle::Radians => x,
+            Angle::Degrees => x.to_degrees(),
+            Angle::Gradians => x / self.radians(),
+        };
+        match self.steps(angle) {
+            Some(steps) => steps as f64 * self.turn() / 24.0,
+            None => angle,
+        }
+    }
+
+    // an angle written in degrees, as in 30°
+    pub fn degrees(&self, x: f64) -> f64 {
+        match *self {
+            Angle::Radians => x.to_radians(),
+            Angle::Degrees => x,
+            Angle::Gradians => x * 10.0 / 9.0,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::f64::consts;
+
+// a named number that can't be assigned to. the unit is only shown for now, until numbers carry
+// units of their own
+pub struct Constant {
+    pub name: &'static str,
+    pub value: f64,
+    pub unit: &'static str,
+    pub description: &'static str,
+}
+
+const fn constant(name: &'static str, value: f64, unit: &'static str, description: &'static str) -> Constant {
+    Constant{ name, value, unit, description }
+}
+
+// names are case insensitive like every other identifier
+pub static MATHEMATICAL: [Constant; 10] = [
+    constant("pi", consts::PI, "", "ratio of a circle's circumference to its diameter"),
+    constant("tau", consts::TAU, "", "ratio of a circle's circumference to its radius"),
+    constant("e", consts::E, "", "base of the natural logarithm"),
+    constant("phi", 1.618033988749895, "", "golden ratio"),
+    constant("euler_gamma", 0.5772156649015329, "", "Euler-Mascheroni constant"),
+    constant("sqrt2", consts::SQRT_2, "", "square root of 2"),
+    constant("inf", f64::INFINITY, "", "infinity"),
+    constant("nan", f64::NAN, "", "not a number"),
+    constant("true", 1.0, "", "boolean true"),
+    constant("false", 0.0, "", "boolean false"),
+];
+
+// CODATA 2018 values in SI units. names are case sensitive so that G doesn't take g, and the
+// elementary charge is q_e because e is already Euler's number
+pub static PHYSICAL: [Constant; 17] = [
+    constant("c", 299792458.0, "m/s", "speed of light in vacuum"),
+    constant("h", 6.62607015e-34, "J s", "Planck constant"),
+    constant("hbar", 1.054571817e-34, "J s", "reduced Planck constant"),
+    constant("G", 6.67430e-11, "m^3/(kg s^2)", "Newtonian constant of gravitation"),
+    constant("g_n", 9.80665, "m/s^2", "standard acceleration of gravity"),
+    constant("k_B", 1.380649e-23, "J/K", "Boltzmann constant"),
+    constant("N_A", 6.02214076e23, "1/mol", "Avogadro constant"),
+    constant("q_e", 1.602176634e-19, "C", "elementary charge, as e is Euler's number"),
+    constant("m_e", 9.1093837015e-31, "kg", "electron mass"),
+    constant("m_p", 1.67262192369e-27, "kg", "proton mass"),
+    constant("m_n", 1.67492749804e-27, "kg", "neutron mass"),
+    constant("m_u", 1.66053906660e-27, "kg", "atomic mass constant"),
+    constant("epsilon_0", 8.8541878128e-12, "F/m", "vacuum electric permittivity"),
+    constant("mu_0", 1.25663706212e-6, "N/A^2", "vacuum magnetic permeability"),
+    constant("sigma_SB", 5.670374419e-8, "W/(m^2 K^4)", "Stefan-Boltzmann constant"),
+    constant("a_0", 5.29177210903e-11, "m", "Bohr radius"),
+    constant("atm", 101325.0, "Pa", "standard atmosphere"),
+];
+
+pub fn lookup(name: &str) -> Option<&'static Constant> {
+    PHYSICAL.iter().find(|constant| constant.name == name)
+        .or_else(|| MATHEMATICAL.iter().find(|constant| constant.name.eq_ignore_ascii_case(name)))
+}
+
+// a table of every constant for the constants command
+pub fn table() -> String {
+    let width = PHYSICAL.iter().chain(MATHEMATICAL.iter()).map(|constant| constant.name.len()).max().unwrap_or(0);
+    let mut lines = Vec::new();
+    for constant in MATHEMATICAL.iter().chain(PHYSICAL.iter()) {
+        let unit = if constant.unit.is_empty() { String::new() } else { format!(" {}", constant.unit) };
+        lines.push(format!("{:width$}  {}{}  {}", constant.name, constant.value, unit, constant.description, width = width));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,339 @@
+use std::f64::consts::{PI, SQRT_2};
+
+use numeric;
+use special::{beta_inc, erfc, gamma_p, gamma_q, lgamma};
+
+// invalid parameters give NaN, which the calculator reports as the function being undefined, so
+// these checks are all false for NaN
+fn is_positive(x: f64) -> bool {
+    x > 0.0
+}
+
+fn is_non_negative(x: f64) -> bool {
+    x >= 0.0
+}
+
+fn is_interval(a: f64, b: f64) -> bool {
+    a < b
+}
+
+fn is_probability(p: f64) -> bool {
+    (0.0..=1.0).contains(&p)
+}
+
+fn is_count(n: f64) -> bool {
+    n >= 0.0 && n.fract() == 0.0
+}
+
+// where a continuous distribution's cdf reaches p, the interval [lower, upper] is widened until
+// it brackets the point
+fn invert(cdf: &dyn Fn(f64) -> f64, p: f64, mut lower: f64, mut upper: f64) -> f64 {
+    let mut width = upper - lower;
+    while cdf(upper) < p {
+        lower = upper;
+        width *= 2.0;
+        upper += width;
+        if upper.is_infinite() {
+            return f64::INFINITY;
+        }
+    }
+    while cdf(lower) > p {
+        upper = lower;
+        width *= 2.0;
+        lower -= width;
+        if lower.is_infinite() {
+            return f64::NEG_INFINITY;
+        }
+    }
+    let mut objective = |x: f64| Ok(cdf(x) - p);
+    numeric::brent(&mut objective, lower, upper, f64::MIN_POSITIVE).unwrap_or(f64::NAN)
+}
+
+// smallest count whose cumulative probability reaches p
+fn invert_discrete(cdf: &dyn Fn(f64) -> f64, p: f64, mut upper: f64) -> f64 {
+    while cdf(upper) < p {
+        upper *= 2.0;
+        if upper.is_infinite() {
+            return f64::INFINITY;
+        }
+    }
+    let mut lower = -1.0;
+    while upper - lower > 1.0 {
+        let middle = ((lower + upper) / 2.0).floor();
+        if cdf(middle) < p { lower = middle } else { upper = middle }
+    }
+    upper
+}
+
+pub fn normal_pdf(x: f64, mean: f64, deviation: f64) -> f64 {
+    if !is_positive(deviation) {
+        return f64::NAN;
+    }
+    let z = (x - mean) / deviation;
+    (-0.5 * z * z).exp() / (deviation * (2.0 * PI).sqrt())
+}
+
+pub fn normal_cdf(x: f64, mean: f64, deviation: f64) -> f64 {
+    if !is_positive(deviation) {
+        return f64::NAN;
+    }
+    0.5 * erfc(-(x - mean) / (deviation * SQRT_2))
+}
+
+// Acklam's rational approximation, polished with a step of Halley's method
+pub fn normal_inv(p: f64, mean: f64, deviation: f64) -> f64 {
+    const A: [f64; 6] = [-39.69683028665376, 220.9460984245205, -275.9285104469687, 138.357751867269, -30.66479806614716, 2.506628277459239];
+    const B: [f64; 5] = [-54.47609879822406, 161.5858368580409, -155.6989798598866, 66.80131188771972, -13.28068155288572];
+    const C: [f64; 6] = [-0.007784894002430293, -0.3223964580411365, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [0.007784695709041462, 0.3224671290700398, 2.445134137142996, 3.754408661907416];
+    const LOW: f64 = 0.02425;
+
+    if !is_positive(deviation) || !is_probability(p) {
+        return f64::NAN;
+    }
+    if p == 0.0 || p == 1.0 {
+        return mean + if p == 0.0 { f64::NEG_INFINITY } else { f64::INFINITY };
+    }
+
+    let tail = |q: f64| (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) /
+                        ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0);
+    let mut z = if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    }
+    else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    }
+    else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q /
+        (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    };
+
+    let error = 0.5 * erfc(-z / SQRT_2) - p;
+    let u = error * (2.0 * PI).sqrt() * (z * z / 2.0).exp();
+    z -= u / (1.0 + z * u / 2.0);
+    mean + deviation * z
+}
+
+pub fn t_pdf(x: f64, freedom: f64) -> f64 {
+    if !is_positive(freedom) {
+        return f64::NAN;
+    }
+    let density = (lgamma((freedom + 1.0) / 2.0) - lgamma(freedom / 2.0)).exp() / (freedom * PI).sqrt();
+    density * (1.0 + x * x / freedom).powf(-(freedom + 1.0) / 2.0)
+}
+
+pub fn t_cdf(x: f64, freedom: f64) -> f64 {
+    if !is_positive(freedom) || x.is_nan() {
+        return f64::NAN;
+    }
+    if x.is_infinite() {
+        return if x > 0.0 { 1.0 } else { 0.0 };
+    }
+    let tail = 0.5 * beta_inc(freedom / 2.0, 0.5, freedom / (freedom + x * x));
+    if x > 0.0 { 1.0 - tail } else { tail }
+}
+
+pub fn t_inv(p: f64, freedom: f64) -> f64 {
+    if !is_positive(freedom) || !is_probability(p) {
+        return f64::NAN;
+    }
+    // the ends of the support, which a search would only approach
+    if p == 0.0 || p == 1.0 {
+        return if p == 0.0 { f64::NEG_INFINITY } else { f64::INFINITY };
+    }
+    if p == 0.5 {
+        return 0.0;
+    }
+    invert(&|x| t_cdf(x, freedom), p, -1.0, 1.0)
+}
+
+pub fn chi2_pdf(x: f64, freedom: f64) -> f64 {
+    if !is_positive(freedom) {
+        return f64::NAN;
+    }
+    let k = freedom / 2.0;
+    if x < 0.0 {
+        return 0.0;
+    }
+    if x == 0.0 {
+        return if k < 1.0 { f64::INFINITY } else if k == 1.0 { 0.5 } else { 0.0 };
+    }
+    ((k - 1.0) * x.ln() - x / 2.0 - k * 2f64.ln() - lgamma(k)).exp()
+}
+
+pub fn chi2_cdf(x: f64, freedom: f64) -> f64 {
+    if !is_positive(freedom) {
+        return f64::NAN;
+    }
+    if x <= 0.0 { 0.0 } else { gamma_p(freedom / 2.0, x / 2.0) }
+}
+
+pub fn chi2_inv(p: f64, freedom: f64) -> f64 {
+    if !is_positive(freedom) || !is_probability(p) {
+        return f64::NAN;
+    }
+    if p == 0.0 || p == 1.0 {
+        return if p == 0.0 { 0.0 } else { f64::INFINITY };
+    }
+    invert(&|x| chi2_cdf(x, freedom), p, 0.0, freedom.max(1.0))
+}
+
+pub fn f_pdf(x: f64, d1: f64, d2: f64) -> f64 {
+    if !is_positive(d1) || !is_positive(d2) {
+        return f64::NAN;
+    }
+    if x < 0.0 {
+        return 0.0;
+    }
+    if x == 0.0 {
+        return if d1 < 2.0 { f64::INFINITY } else if d1 == 2.0 { 1.0 } else { 0.0 };
+    }
+    let log_beta = lgamma(d1 / 2.0) + lgamma(d2 / 2.0) - lgamma((d1 + d2) / 2.0);
+    (0.5 * (d1 * (d1 * x).ln() + d2 * d2.ln() - (d1 + d2) * (d1 * x + d2).ln()) - x.ln() - log_beta).exp()
+}
+
+pub fn f_cdf(x: f64, d1: f64, d2: f64) -> f64 {
+    if !is_positive(d1) || !is_positive(d2) {
+        return f64::NAN;
+    }
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x.is_infinite() {
+        return 1.0;
+    }
+    beta_inc(d1 / 2.0, d2 / 2.0, d1 * x / (d1 * x + d2))
+}
+
+pub fn f_inv(p: f64, d1: f64, d2: f64) -> f64 {
+    if !is_positive(d1) || !is_positive(d2) || !is_probability(p) {
+        return f64::NAN;
+    }
+    if p == 0.0 || p == 1.0 {
+        return if p == 0.0 { 0.0 } else { f64::INFINITY };
+    }
+    invert(&|x| f_cdf(x, d1, d2), p, 0.0, 1.0)
+}
+
+pub fn binomial_pdf(k: f64, n: f64, p: f64) -> f64 {
+    if !is_count(n) || !is_probability(p) {
+        return f64::NAN;
+    }
+    if !is_count(k) || k > n {
+        return 0.0;
+    }
+    if p == 0.0 || p == 1.0 {
+        let certain = if p == 0.0 { 0.0 } else { n };
+        return if k == certain { 1.0 } else { 0.0 };
+    }
+    (lgamma(n + 1.0) - lgamma(k + 1.0) - lgamma(n - k + 1.0) + k * p.ln() + (n - k) * (1.0 - p).ln()).exp()
+}
+
+pub fn binomial_cdf(k: f64, n: f64, p: f64) -> f64 {
+    if !is_count(n) || !is_probability(p) || k.is_nan() {
+        return f64::NAN;
+    }
+    let k = k.floor();
+    if k < 0.0 {
+        return 0.0;
+    }
+    if k >= n || p == 0.0 {
+        return 1.0;
+    }
+    if p == 1.0 {
+        return 0.0;
+    }
+    beta_inc(n - k, k + 1.0, 1.0 - p)
+}
+
+pub fn binomial_inv(q: f64, n: f64, p: f64) -> f64 {
+    if !is_count(n) || !is_probability(p) || !is_probability(q) {
+        return f64::NAN;
+    }
+    // every trial fails when p is 0, so the support ends at 0 rather than n
+    if q == 1.0 {
+        return if p == 0.0 { 0.0 } else { n };
+    }
+    invert_discrete(&|k| binomial_cdf(k, n, p), q, n)
+}
+
+pub fn poisson_pdf(k: f64, rate: f64) -> f64 {
+    if !is_non_negative(rate) {
+        return f64::NAN;
+    }
+    if !is_count(k) {
+        return 0.0;
+    }
+    if rate == 0.0 {
+        return if k == 0.0 { 1.0 } else { 0.0 };
+    }
+    (k * rate.ln() - rate - lgamma(k + 1.0)).exp()
+}
+
+pub fn poisson_cdf(k: f64, rate: f64) -> f64 {
+    if !is_non_negative(rate) || k.is_nan() {
+        return f64::NAN;
+    }
+    let k = k.floor();
+    if k < 0.0 {
+        return 0.0;
+    }
+    if rate == 0.0 || k.is_infinite() {
+        return 1.0;
+    }
+    gamma_q(k + 1.0, rate)
+}
+
+pub fn poisson_inv(p: f64, rate: f64) -> f64 {
+    if !is_non_negative(rate) || !is_probability(p) {
+        return f64::NAN;
+    }
+    if p == 1.0 {
+        return if rate == 0.0 { 0.0 } else { f64::INFINITY };
+    }
+    invert_discrete(&|k| poisson_cdf(k, rate), p, rate.max(1.0))
+}
+
+pub fn exponential_pdf(x: f64, rate: f64) -> f64 {
+    if !is_positive(rate) {
+        return f64::NAN;
+    }
+    if x < 0.0 { 0.0 } else { rate * (-rate * x).exp() }
+}
+
+pub fn exponential_cdf(x: f64, rate: f64) -> f64 {
+    if !is_positive(rate) {
+        return f64::NAN;
+    }
+    if x < 0.0 { 0.0 } else { -(-rate * x).exp_m1() }
+}
+
+pub fn exponential_inv(p: f64, rate: f64) -> f64 {
+    if !is_positive(rate) || !is_probability(p) {
+        return f64::NAN;
+    }
+    -(-p).ln_1p() / rate
+}
+
+pub fn uniform_pdf(x: f64, a: f64, b: f64) -> f64 {
+    if !is_interval(a, b) {
+        return f64::NAN;
+    }
+    if x < a || x > b { 0.0 } else { 1.0 / (b - a) }
+}
+
+pub fn uniform_cdf(x: f64, a: f64, b: f64) -> f64 {
+    if !is_interval(a, b) || x.is_nan() {
+        return f64::NAN;
+    }
+    ((x - a) / (b - a)).clamp(0.0, 1.0)
+}
+
+pub fn uniform_inv(p: f64, a: f64, b: f64) -> f64 {
+    if !is_interval(a, b) || !is_probability(p) {
+        return f64::NAN;
+    }
+    a + p * (b - a)
+}
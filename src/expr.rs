@@ -0,0 +1,180 @@
+use std::fmt;
+
+use super::{Associativity, Operation, Precedence};
+
+// expression tree built from the parsed tokens, operators and functions are both calls
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    // integer literal of programmer mode
+    Integer(u128),
+    Variable(String),
+    List(Vec<Expr>),
+    Call(Operation, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn as_variable(&self) -> Option<&str> {
+        match *self {
+            Expr::Variable(ref name) => Some(name),
+            _ => None,
+        }
+    }
+
+    // names of the parameters of a lambda, which are either a single variable or a list
+    pub fn parameter_names(&self) -> Result<Vec<String>, String> {
+        match *self {
+            Expr::Variable(ref name) => Ok(vec![name.clone()]),
+            Expr::List(ref items) => items.iter().map(|item| match *item {
+                Expr::Variable(ref name) => Ok(name.clone()),
+                _ => Err(String::from("Lambda parameters must be variables")),
+            }).collect(),
+            _ => Err(String::from("Lambda parameters must be variables")),
+        }
+    }
+
+    // collects the variables used in the expression which aren't bound inside of it
+    pub fn free_variables(&self, bound: &mut Vec<String>, output: &mut Vec<String>) {
+        match *self {
+            Expr::Number(..) | Expr::Integer(..) => { },
+            Expr::Variable(ref name) => {
+                if !bound.contains(name) && !output.contains(name) {
+                    output.push(name.clone());
+                }
+            },
+            Expr::List(ref items) => {
+                for item in items {
+                    item.free_variables(bound, output);
+                }
+            },
+            Expr::Call(Operation::Lambda, ref args) => {
+                let parameters = args[0].parameter_names().unwrap_or_default();
+                let depth = bound.len();
+                bound.extend(parameters);
+                args[1].free_variables(bound, output);
+                bound.truncate(depth);
+            },
+            Expr::Call(operation, ref args) => {
+                let binding = operation.binding();
+                for (i, arg) in args.iter().enumerate() {
+                    match binding {
+                        Some((variable, _)) if variable == i => { },
+                        Some((variable, body)) if body == i => {
+                            let depth = bound.len();
+                            bound.extend(args.get(variable).and_then(|arg| arg.parameter_names().ok()).unwrap_or_default());
+                            arg.free_variables(bound, output);
+                            bound.truncate(depth);
+                        },
+                        _ => arg.free_variables(bound, output),
+                    }
+                }
+            },
+        }
+    }
+}
+
+// binds tighter than any operator
+const ATOM: u32 = u32::MAX;
+
+// precedence of an expression when it's the operand of an operator
+fn precedence(expr: &Expr) -> u32 {
+    match *expr {
+        // negative numbers print with a unary minus
+        Expr::Number(value) if value < 0.0 => Precedence::Unary as u32,
+        Expr::Call(operation, _) => match operation.operator_precedence() {
+            Some((precedence, _)) => precedence as u32,
+            None => ATOM,
+        },
+        _ => ATOM,
+    }
+}
+
+// operands that bind looser than the operator, or equally loose on the side the operator
+// doesn't associate to, are wrapped in parentheses
+fn write_operand(f: &mut fmt::Formatter, expr: &Expr, minimum: u32, inclusive: bool) -> fmt::Result {
+    let operand = precedence(expr);
+    if operand < minimum || (inclusive && operand == minimum) {
+        write!(f, "({})", expr)
+    }
+    else {
+        write!(f, "{}", expr)
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, items: &[Expr]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (operation, args) = match *self {
+            Expr::Number(value) => return write!(f, "{}", value),
+            Expr::Integer(value) => return write!(f, "{}", value),
+            Expr::Variable(ref name) => return write!(f, "{}", name),
+            Expr::List(ref items) => {
+                write!(f, "[")?;
+                write_list(f, items)?;
+                return write!(f, "]");
+            },
+            Expr::Call(operation, ref args) => (operation, args),
+        };
+
+        let (precedence, associativity) = match operation.operator_precedence() {
+            Some((precedence, associativity)) => (precedence as u32, associativity),
+            None => {
+                if let Operation::Call = operation {
+                    write_operand(f, &args[0], ATOM, false)?;
+                    write!(f, "(")?;
+                    write_list(f, &args[1..])?;
+                    return write!(f, ")");
+                }
+                if let Operation::Index = operation {
+                    write_operand(f, &args[0], ATOM, false)?;
+                    return write!(f, "[{}]", args[1]);
+                }
+                write!(f, "{}(", operation.name())?;
+                write_list(f, args)?;
+                return write!(f, ")");
+            },
+        };
+
+        match operation {
+            Operation::Negation | Operation::Identity | Operation::BitNot => {
+                write!(f, "{}", operation.name())?;
+                write_operand(f, &args[0], precedence, false)
+            },
+            Operation::Not | Operation::Const => {
+                write!(f, "{} ", operation.name())?;
+                write_operand(f, &args[0], precedence, false)
+            },
+            Operation::Factorial | Operation::DegreeSign => {
+                write_operand(f, &args[0], precedence, false)?;
+                write!(f, "{}", operation.name())
+            },
+            Operation::Lambda => {
+                match args[0] {
+                    Expr::List(ref parameters) => {
+                        write!(f, "(")?;
+                        write_list(f, parameters)?;
+                        write!(f, ")")?;
+                    },
+                    ref parameter => write!(f, "{}", parameter)?,
+                }
+                write!(f, " -> ")?;
+                write_operand(f, &args[1], precedence, false)
+            },
+            _ => {
+                let right = matches!(associativity, Associativity::Right);
+                write_operand(f, &args[0], precedence, right)?;
+                write!(f, " {} ", operation.name())?;
+                write_operand(f, &args[1], precedence, !right)
+            },
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use numeric::{self, SolveError};
+
+// time value of money with spreadsheet conventions: money paid out is negative, money received
+// positive, and payments fall at the end of each period unless the timing is 1 for the beginning
+
+fn timing(when: f64) -> Result<f64, String> {
+    match when {
+        0.0 | 1.0 => Ok(when),
+        _ => Err(String::from("Payment timing must be 0 for the end or 1 for the beginning of each period")),
+    }
+}
+
+// growth of one unit over the periods and the factor that sums the payments
+fn factors(rate: f64, periods: f64, when: f64) -> (f64, f64) {
+    let growth = (1.0 + rate).powf(periods);
+    let annuity = if rate == 0.0 { periods } else { (1.0 + rate * when) * (growth - 1.0) / rate };
+    (growth, annuity)
+}
+
+pub fn pv(rate: f64, periods: f64, payment: f64, future: f64, when: f64) -> Result<f64, String> {
+    let (growth, annuity) = factors(rate, periods, timing(when)?);
+    Ok(-(future + payment * annuity) / growth)
+}
+
+pub fn fv(rate: f64, periods: f64, payment: f64, present: f64, when: f64) -> Result<f64, String> {
+    let (growth, annuity) = factors(rate, periods, timing(when)?);
+    Ok(-(present * growth + payment * annuity))
+}
+
+pub fn pmt(rate: f64, periods: f64, present: f64, future: f64, when: f64) -> Result<f64, String> {
+    let (growth, annuity) = factors(rate, periods, timing(when)?);
+    Ok(-(present * growth + future) / annuity)
+}
+
+pub fn nper(rate: f64, payment: f64, present: f64, future: f64, when: f64) -> Result<f64, String> {
+    let when = timing(when)?;
+    if rate == 0.0 {
+        // without interest or payments the balance never changes, so no number of periods is
+        // defined, and NaN makes it undefined
+        if payment == 0.0 {
+            return Ok(f64::NAN);
+        }
+        return Ok(-(present + future) / payment);
+    }
+    let payment = payment * (1.0 + rate * when);
+    let periods = ((payment - future * rate) / (payment + present * rate)).ln() / (1.0 + rate).ln();
+    if periods.is_nan() {
+        return Err(String::from("The payments can never reach the future value"));
+    }
+    Ok(periods)
+}
+
+// the solvers only look at rates above -100%, where the discounting is defined
+fn check_rate(result: Result<f64, SolveError>) -> Result<f64, String> {
+    match result {
+        Ok(rate) if rate > -1.0 => Ok(rate),
+        Ok(_) => Err(String::from("No rate above -100% was found, try another guess")),
+        Err(error) => Err(format!("Could not find the rate: {}", error)),
+    }
+}
+
+pub fn rate(periods: f64, payment: f64, present: f64, future: f64, when: f64, guess: f64) -> Result<f64, String> {
+    let when = timing(when)?;
+    let mut balance = |rate: f64| {
+        let (growth, annuity) = factors(rate, periods, when);
+        Ok(present * growth + payment * annuity + future)
+    };
+    check_rate(numeric::root(&mut balance, guess, 1e-12))
+}
+
+// discounts the first value by a whole period, like spreadsheets do
+pub fn npv(rate: f64, values: &[f64]) -> f64 {
+    values.iter().enumerate().map(|(i, value)| value / (1.0 + rate).powi(i as i32 + 1)).sum()
+}
+
+fn check_flows(values: &[f64]) -> Result<(), String> {
+    if !values.iter().any(|&v| v > 0.0) || !values.iter().any(|&v| v < 0.0) {
+        return Err(String::from("Cash flows need at least one positive and one negative value"));
+    }
+    Ok(())
+}
+
+// rate at which the cash flows, the first of them undiscounted, have a net present value of zero
+pub fn irr(values: &[f64], guess: f64) -> Result<f64, String> {
+    check_flows(values)?;
+    let mut present = |rate: f64| Ok(values.iter().enumerate().map(|(i, value)| value / (1.0 + rate).powi(i as i32)).sum());
+    check_rate(numeric::root(&mut present, guess, 1e-12))
+}
+
+// irr for cash flows on irregular dates, given as day numbers and discounted by 365-day years
+pub fn xirr(values: &[f64], dates: &[f64], guess: f64) -> Result<f64, String> {
+    if values.len() != dates.len() {
+        return Err(format!("Expected {} dates but found {}", values.len(), dates.len()));
+    }
+    check_flows(values)?;
+    let start = dates.iter().cloned().fold(f64::INFINITY, f64::min);
+    let mut present = |rate: f64| Ok(values.iter().zip(dates).map(|(value, date)| value / (1.0 + rate).powf((date - start) / 365.0)).sum());
+    check_rate(numeric::root(&mut present, guess, 1e-12))
+}
+
+// schedule of a loan paid off in equal payments at the end of each period, with a row of
+// [period, payment, interest, principal, balance] for each of them
+pub fn amortize(principal: f64, rate: f64, periods: f64) -> Result<Vec<[f64; 5]>, String> {
+    if periods < 1.0 || periods.fract() != 0.0 {
+        return Err(String::from("The number of periods must be a positive integer"));
+    }
+    let payment = -pmt(rate, periods, principal, 0.0, 0.0)?;
+    let mut balance = principal;
+    let mut rows = Vec::new();
+    for period in 1..=periods as usize {
+        let interest = balance * rate;
+        let repaid = payment - interest;
+        balance -= repaid;
+        // what's left after the last payment is only rounding
+        if period == periods as usize {
+            balance = 0.0;
+        }
+        rows.push([period as f64, payment, interest, repaid, balance]);
+    }
+    Ok(rows)
+}
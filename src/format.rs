@@ -0,0 +1,269 @@
+use value::Value;
+
+// how many digits a number is shown with
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Precision {
+    // as few digits as read back to the same number
+    Shortest,
+    // digits after the point
+    Fixed(usize),
+    // significant digits, without trailing zeros
+    Significant(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Notation {
+    Plain,
+    Scientific,
+    // exponents that are multiples of three
+    Engineering,
+    // engineering notation with a prefix like the k in 4.7k
+    Si,
+}
+
+// the settings results are printed with, the default prints numbers just like Display does
+#[derive(Debug, Clone, PartialEq)]
+pub struct Format {
+    pub precision: Precision,
+    pub notation: Notation,
+    // groups the digits before the point in threes
+    pub separator: Option<char>,
+    pub radix: u32,
+    // shows numbers as fractions when one with a small denominator gives the same number
+    pub fraction: bool,
+}
+
+// a double has at most 17 significant decimal digits, anything further would be made up
+const MAX_DIGITS: usize = 17;
+
+const MAX_DECIMALS: usize = 100;
+
+// larger denominators would fit almost any number that closely, fractions or not
+const MAX_DENOMINATOR: f64 = 1e6;
+
+// the prefixes from quecto to quetta, a thousand times apart
+const SI_PREFIXES: [&str; 21] = ["q", "r", "y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T", "P", "E", "Z", "Y", "R", "Q"];
+
+impl Default for Format {
+    fn default() -> Format {
+        Format{ precision: Precision::Shortest, notation: Notation::Plain, separator: None, radix: 10, fraction: false }
+    }
+}
+
+// the mantissa and exponent of a number in scientific notation, with some decimals or the fewest
+// that read back to it
+fn scientific(x: f64, decimals: Option<usize>) -> (String, i32) {
+    let text = match decimals {
+        Some(decimals) => format!("{:.*e}", decimals, x),
+        None => format!("{:e}", x),
+    };
+    let (mantissa, exponent) = text.split_at(text.find('e').unwrap_or(text.len()));
+    (mantissa.to_string(), exponent[1..].parse().unwrap_or(0))
+}
+
+// moves the point of a mantissa by a number of places
+fn shift_point(mantissa: &str, places: i32) -> String {
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|&ch| ch != '.').collect();
+    let point = mantissa.find('.').unwrap_or(mantissa.len()) as i32 + places;
+    let text = if point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    }
+    else if point as usize >= digits.len() {
+        format!("{}{}", digits, "0".repeat(point as usize - digits.len()))
+    }
+    else {
+        format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+    };
+    format!("{}{}", sign, text)
+}
+
+fn trim_zeros(text: &str) -> &str {
+    if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.') } else { text }
+}
+
+fn group(text: &str, separator: char) -> String {
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text),
+    };
+    let end = text.find('.').unwrap_or(text.len());
+    let mut grouped = String::from(sign);
+    for (i, ch) in text[..end].chars().enumerate() {
+        if i > 0 && (end - i) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(ch);
+    }
+    grouped + &text[end..]
+}
+
+// closest fraction by continued fractions whose value is the number itself, give or take rounding
+fn fraction(x: f64) -> Option<(f64, f64)> {
+    let (mut h, mut previous_h) = (1.0, 0.0);
+    let (mut k, mut previous_k) = (0.0, 1.0);
+    let mut rest = x.abs();
+    for _ in 0..64 {
+        let a = rest.floor();
+        (h, previous_h) = (a * h + previous_h, h);
+        (k, previous_k) = (a * k + previous_k, k);
+        if k > MAX_DENOMINATOR {
+            return None;
+        }
+        if (h / k - x.abs()).abs() <= 4.0 * f64::EPSILON * x.abs() {
+            return Some((h.copysign(x), k));
+        }
+        rest = 1.0 / (rest - a);
+        if !rest.is_finite() {
+            return None;
+        }
+    }
+    None
+}
+
+impl Format {
+    pub fn number(&self, x: f64) -> String {
+        if !x.is_finite() {
+            return x.to_string();
+        }
+        if self.fraction && x.fract() != 0.0 {
+            if let Some((numerator, denominator)) = fraction(x) {
+                return format!("{}/{}", numerator, denominator);
+            }
+        }
+        if self.radix != 10 {
+            if let Some(text) = self.radix_digits(x) {
+                return text;
+            }
+        }
+
+        let significant = match self.precision {
+            Precision::Significant(digits) => Some(digits.clamp(1, MAX_DIGITS) - 1),
+            _ => None,
+        };
+        let text = match self.notation {
+            Notation::Plain => match self.precision {
+                Precision::Shortest => x.to_string(),
+                Precision::Fixed(decimals) => format!("{:.*}", decimals.min(MAX_DECIMALS), x),
+                Precision::Significant(..) => {
+                    let (mantissa, exponent) = scientific(x, significant);
+                    trim_zeros(&shift_point(&mantissa, exponent)).to_string()
+                },
+            },
+            notation => {
+                let decimals = match self.precision {
+                    Precision::Fixed(decimals) => Some(decimals.min(MAX_DECIMALS)),
+                    _ => significant,
+                };
+                let (mut mantissa, mut exponent) = scientific(x, decimals);
+                if notation != Notation::Scientific {
+                    let places = exponent.rem_euclid(3);
+                    mantissa = shift_point(&mantissa, places);
+                    exponent -= places;
+                }
+                if significant.is_some() {
+                    mantissa = trim_zeros(&mantissa).to_string();
+                }
+                let prefix = SI_PREFIXES.get((exponent / 3 + 10) as usize).filter(|_| exponent.abs() <= 30);
+                match (notation, prefix) {
+                    (Notation::Si, Some(prefix)) => format!("{}{}", mantissa, prefix),
+                    _ => format!("{}e{}", mantissa, exponent),
+                }
+            },
+        };
+
+        match self.separator {
+            Some(separator) if self.notation == Notation::Plain => group(&text, separator),
+            _ => text,
+        }
+    }
+
+    // the number in another base, rounded to the digits after the point, which are as many as the
+    // precision asks for or enough for every bit of the fraction. the usual prefixes mark bases 2,
+    // 8 and 16 and others are written as a suffix
+    fn radix_digits(&self, x: f64) -> Option<String> {
+        let radix = self.radix as f64;
+        let mut decimals = match self.precision {
+            Precision::Fixed(decimals) => decimals,
+            _ => (53.0 / radix.log2()).ceil() as usize,
+        };
+        // the digits are taken from an integer of up to 128 bits
+        let limit = 2f64.powi(128);
+        while decimals > 0 && (x.abs() * radix.powi(decimals as i32)).round() >= limit {
+            decimals -= 1;
+        }
+        let scaled = (x.abs() * radix.powi(decimals as i32)).round();
+        if scaled >= limit {
+            return None;
+        }
+
+        let mut value = scaled as u128;
+        let mut digits = Vec::new();
+        while value > 0 || digits.len() <= decimals {
+            digits.push(std::char::from_digit((value % self.radix as u128) as u32, self.radix).unwrap_or('?'));
+            value /= self.radix as u128;
+        }
+        digits.reverse();
+        let text: String = digits.iter().collect();
+        let (whole, fraction) = text.split_at(text.len() - decimals);
+        let mut text = format!("{}.{}", whole, fraction);
+        text = match self.precision {
+            Precision::Fixed(..) => text.trim_end_matches('.').to_string(),
+            _ => trim_zeros(&text).to_string(),
+        };
+
+        let sign = if x < 0.0 { "-" } else { "" };
+        Some(match self.radix {
+            2 => format!("{}0b{}", sign, text),
+            8 => format!("{}0o{}", sign, text),
+            16 => format!("{}0x{}", sign, text),
+            _ => format!("{}{}_{}", sign, text, self.radix),
+        })
+    }
+
+    // a value with its numbers formatted, and everything else as Display shows it
+    pub fn value(&self, value: &Value) -> String {
+        match *value {
+            Value::Number(x) => self.number(x),
+            Value::List(ref items) => {
+                let items: Vec<String> = items.iter().map(|item| self.value(item)).collect();
+                format!("[{}]", items.join(", "))
+            },
+            ref value => value.to_string(),
+        }
+    }
+
+    // changes one setting, by the name and value the format() function takes
+    pub fn set(&mut self, setting: &str, value: Option<f64>) -> Result<(), String> {
+        let count = |limit: usize| match value {
+            Some(x) if x.fract() == 0.0 && (0.0..=limit as f64).contains(&x) => Ok(x as usize),
+            Some(x) => Err(format!("{} must be an integer from 0 to {}, not {}", setting, limit, x)),
+            None => Err(format!("{} needs a value", setting)),
+        };
+        let flag = value.is_none_or(|x| x != 0.0);
+        match setting {
+            "digits" if value.is_none() => self.precision = Precision::Shortest,
+            "digits" => self.precision = Precision::Significant(count(MAX_DIGITS)?.max(1)),
+            "fixed" => self.precision = Precision::Fixed(count(MAX_DECIMALS)?),
+            "plain" => self.notation = Notation::Plain,
+            "sci" => self.notation = Notation::Scientific,
+            "eng" => self.notation = Notation::Engineering,
+            "si" => self.notation = Notation::Si,
+            "separators" => self.separator = if flag { Some(',') } else { None },
+            "fraction" => self.fraction = flag,
+            "radix" => {
+                let radix = count(36)?;
+                if radix < 2 {
+                    return Err(format!("radix must be from 2 to 36, not {}", radix));
+                }
+                self.radix = radix as u32;
+            },
+            "default" => *self = Format::default(),
+            _ => return Err(format!("Unknown format setting '{}'", setting)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+// number theory on exact integers, the products are taken in 128 bits so they can't overflow
+
+// enough witnesses for Miller-Rabin to be deterministic below 2^64
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+// beyond 2^53 not every integer is a double, so larger ones may already have been rounded
+const MAX_EXACT: i128 = 1 << 53;
+
+// the integer a number holds, if it holds one that's known exactly
+pub fn from_f64(x: f64) -> Option<i128> {
+    if x.fract() == 0.0 && x.abs() <= MAX_EXACT as f64 {
+        Some(x as i128)
+    }
+    else {
+        None
+    }
+}
+
+// the number an integer result is, if a double holds it exactly
+pub fn exact_f64(n: i128) -> Option<f64> {
+    if n.abs() <= MAX_EXACT { Some(n as f64) } else { None }
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+pub fn pow_mod(base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut base = base % m;
+    let mut result = 1 % m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+// greatest common divisor g along with x and y such that a*x + b*y = g
+pub fn egcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_x, x) = (x, old_x - quotient * x);
+        (old_y, y) = (y, old_y - quotient * y);
+    }
+    // the divisor is reported as positive
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    }
+    else {
+        (old_r, old_x, old_y)
+    }
+}
+
+pub fn mod_inverse(a: i128, m: u64) -> Option<u64> {
+    let (g, x, _) = egcd(a.rem_euclid(m as i128), m as i128);
+    if g != 1 {
+        return None;
+    }
+    Some(x.rem_euclid(m as i128) as u64)
+}
+
+// deterministic Miller-Rabin
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in &WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    let shift = (n - 1).trailing_zeros();
+    let odd = (n - 1) >> shift;
+    'witness: for &a in &WITNESSES {
+        let mut x = pow_mod(a, odd, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..shift {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// smallest prime above n, if there's one below 2^64
+pub fn next_prime(n: u64) -> Option<u64> {
+    let mut candidate = n.checked_add(1)?;
+    while !is_prime(candidate) {
+        candidate = candidate.checked_add(1)?;
+    }
+    Some(candidate)
+}
+
+// a non-trivial divisor of an odd composite by Pollard's rho with Brent's cycle detection
+fn pollard_rho(n: u64) -> u64 {
+    let mut increment = 1;
+    loop {
+        let f = |x: u64| ((mul_mod(x, x, n) as u128 + increment) % n as u128) as u64;
+        let (mut x, mut y) = (2, 2);
+        let mut divisor = 1;
+        let mut power = 1;
+        let mut length = 0;
+        while divisor == 1 {
+            if length == power {
+                x = y;
+                power *= 2;
+                length = 0;
+            }
+            y = f(y);
+            length += 1;
+            divisor = gcd(x.abs_diff(y), n);
+        }
+        if divisor != n {
+            return divisor;
+        }
+        // the sequence cycled without splitting n, so start over with another polynomial
+        increment += 1;
+    }
+}
+
+// prime factors from smallest to largest, repeated by their multiplicity
+pub fn factor(n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    let mut n = n;
+    for &p in &WITNESSES {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+    }
+
+    let mut pending = vec![n];
+    while let Some(m) = pending.pop() {
+        if m == 1 {
+            continue;
+        }
+        if is_prime(m) {
+            factors.push(m);
+            continue;
+        }
+        let divisor = pollard_rho(m);
+        pending.push(divisor);
+        pending.push(m / divisor);
+    }
+    factors.sort_unstable();
+    factors
+}
+
+// primes paired with their multiplicities
+fn prime_powers(n: u64) -> Vec<(u64, u32)> {
+    let mut powers: Vec<(u64, u32)> = Vec::new();
+    for p in factor(n) {
+        match powers.last_mut() {
+            Some((q, count)) if *q == p => *count += 1,
+            _ => powers.push((p, 1)),
+        }
+    }
+    powers
+}
+
+pub fn totient(n: u64) -> u64 {
+    prime_powers(n).iter().fold(n, |result, &(p, _)| result / p * (p - 1))
+}
+
+pub fn divisors(n: u64) -> Vec<u64> {
+    let mut divisors = vec![1];
+    for (p, count) in prime_powers(n) {
+        let previous = divisors.len();
+        let mut power = 1;
+        for _ in 0..count {
+            power *= p;
+            for i in 0..previous {
+                divisors.push(divisors[i] * power);
+            }
+        }
+    }
+    divisors.sort_unstable();
+    divisors
+}
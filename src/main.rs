@@ -1,15 +1,47 @@
 #![allow(dead_code)]
 
-use std::io::{self, Write};
+mod angle;
+mod constants;
+mod expr;
+mod finance;
+mod format;
+mod distribution;
+mod integer;
+mod numeric;
+mod polynomial;
+mod random;
+mod session;
+mod special;
+mod symbolic;
+mod value;
+mod word;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use angle::Angle;
+use expr::Expr;
+use format::Format;
+use polynomial::Polynomial;
+use random::Random;
+use value::{Lambda, Value};
+use word::{Overflow, Word, WordMode};
 
 #[derive(Debug, Copy, Clone)]
 enum Precedence {
+	Declaration,
 	Assignment,
+	Lambda,
 	LogOr,
 	LogXor,
 	LogAnd,
 	Equality,
 	Relational,
+	BitOr,
+	BitXor,
+	BitAnd,
+	Shift,
 	Additive,
 	Multiplicative,
 	Power,
@@ -24,12 +56,13 @@ enum Associativity {
     Right,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 enum Operation {
     // unary operators
     Negation,
     Identity,
     Not,
+    Const,
 
     // binary operators
     Addition,
@@ -38,6 +71,7 @@ enum Operation {
     Division,
     Modulus,
     Assignment,
+    Define,
     Equality,
     Inequality,
     Less,
@@ -50,6 +84,7 @@ enum Operation {
     Nor,
     Power,
     Factorial,
+    Lambda,
 
     // single-arg functions
     Sin,
@@ -66,33 +101,695 @@ enum Operation {
     Sign,
     Result,
 
+    Length,
+
     // multi-arg functions
     Min,
     Max,
+    Range,
+    Map,
+    Filter,
+    Reduce,
+    SumOver,
+    Call,
+    Sum,
+    Product,
+    Diff,
+    Simplify,
+    Integrate,
+    Index,
+    Equation,
+    Solve,
+    Root,
+    Poly,
+    PolyDiv,
+    PolyGcd,
+    PolyDer,
+    Roots,
+    Coeffs,
+    Degree,
+    Ode,
+    Minimize,
+    Maximize,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sinh,
+    Cosh,
+    Tanh,
+    Asinh,
+    Acosh,
+    Atanh,
+    Floor,
+    Ceil,
+    Round,
+    Trunc,
+    Frac,
+    Hypot,
+    Cbrt,
+    NthRoot,
+    Log,
+    Gcd,
+    Lcm,
+    Ncr,
+    Npr,
+    Clamp,
+    Lerp,
+    Gamma,
+    LogGamma,
+    Beta,
+    Digamma,
+    NormPdf,
+    NormCdf,
+    NormInv,
+    TPdf,
+    TCdf,
+    TInv,
+    Chi2Pdf,
+    Chi2Cdf,
+    Chi2Inv,
+    FPdf,
+    FCdf,
+    FInv,
+    BinomPdf,
+    BinomCdf,
+    BinomInv,
+    PoissPdf,
+    PoissCdf,
+    PoissInv,
+    ExpPdf,
+    ExpCdf,
+    ExpInv,
+    UnifPdf,
+    UnifCdf,
+    UnifInv,
+    Erf,
+    Erfc,
+    Rand,
+    RandInt,
+    RandN,
+    Choice,
+    Seed,
+    Pv,
+    Fv,
+    Pmt,
+    Nper,
+    Rate,
+    Npv,
+    Irr,
+    Xirr,
+    Amortize,
+    IsPrime,
+    Factor,
+    NextPrime,
+    ModPow,
+    ModInv,
+    Totient,
+    Divisors,
+    Egcd,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    RotateLeft,
+    RotateRight,
+    Word,
+    Format,
+    DegreeSign,
+    AngleMode,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl Operation {
+    // minimum and maximum number of arguments accepted by a function
+    fn arity(&self) -> (u32, u32) {
+        match *self {
+            Operation::Min | Operation::Max | Operation::Power => (2, 2),
+            Operation::Map | Operation::Filter | Operation::SumOver => (2, 2),
+            Operation::Range | Operation::Reduce => (2, 3),
+            Operation::Sum | Operation::Product => (4, 5),
+            Operation::Diff => (2, 3),
+            Operation::Simplify | Operation::Index => (2, 2),
+            Operation::Integrate => (4, 5),
+            Operation::Solve => (2, 4),
+            Operation::Root => (3, 4),
+            Operation::Poly => (1, 2),
+            Operation::PolyDiv | Operation::PolyGcd => (2, 2),
+            Operation::Ode => (4, 5),
+            Operation::Minimize | Operation::Maximize => (3, 6),
+            Operation::Atan2 | Operation::Hypot | Operation::NthRoot => (2, 2),
+            Operation::Gcd | Operation::Lcm | Operation::Ncr | Operation::Npr => (2, 2),
+            Operation::Round | Operation::Log => (1, 2),
+            Operation::Clamp | Operation::Lerp => (3, 3),
+            Operation::Beta => (2, 2),
+            Operation::NormPdf | Operation::NormCdf | Operation::NormInv => (1, 3),
+            Operation::TPdf | Operation::TCdf | Operation::TInv => (2, 2),
+            Operation::Chi2Pdf | Operation::Chi2Cdf | Operation::Chi2Inv => (2, 2),
+            Operation::FPdf | Operation::FCdf | Operation::FInv => (3, 3),
+            Operation::BinomPdf | Operation::BinomCdf | Operation::BinomInv => (3, 3),
+            Operation::PoissPdf | Operation::PoissCdf | Operation::PoissInv => (2, 2),
+            Operation::ExpPdf | Operation::ExpCdf | Operation::ExpInv => (1, 2),
+            Operation::UnifPdf | Operation::UnifCdf | Operation::UnifInv => (1, 3),
+            Operation::Rand => (0, 0),
+            Operation::RandInt => (2, 2),
+            Operation::RandN => (0, 2),
+            Operation::Pv | Operation::Fv | Operation::Pmt | Operation::Nper => (3, 5),
+            Operation::Rate => (3, 6),
+            Operation::Npv => (2, 2),
+            Operation::Irr => (1, 2),
+            Operation::Xirr => (2, 3),
+            Operation::Amortize => (3, 3),
+            Operation::ModPow => (3, 3),
+            Operation::ModInv | Operation::Egcd => (2, 2),
+            Operation::RotateLeft | Operation::RotateRight => (2, 2),
+            Operation::Word => (1, 3),
+            Operation::Format => (1, 2),
+            Operation::Call => (0, u32::MAX),
+            _ => (1, 1),
+        }
+    }
+
+    // argument positions of the variable bound by a function and the expression it's bound in
+    fn binding(&self) -> Option<(usize, usize)> {
+        match *self {
+            Operation::Sum | Operation::Product => Some((0, 3)),
+            Operation::Diff | Operation::Simplify | Operation::Integrate => Some((1, 0)),
+            Operation::Solve | Operation::Root | Operation::Poly => Some((1, 0)),
+            Operation::Minimize | Operation::Maximize => Some((1, 0)),
+            _ => None,
+        }
+    }
+
+    // precedence and associativity of operations written as operators, the same as the tokenizer
+    // gives them
+    fn operator_precedence(&self) -> Option<(Precedence, Associativity)> {
+        match *self {
+            Operation::Negation | Operation::Identity | Operation::Not | Operation::BitNot => Some((Precedence::Unary, Associativity::None)),
+            Operation::Factorial | Operation::DegreeSign => Some((Precedence::Postfix, Associativity::None)),
+            Operation::Power => Some((Precedence::Power, Associativity::Right)),
+            Operation::Multiplication | Operation::Division | Operation::Modulus => Some((Precedence::Multiplicative, Associativity::Left)),
+            Operation::Addition | Operation::Subtraction => Some((Precedence::Additive, Associativity::Left)),
+            Operation::ShiftLeft | Operation::ShiftRight => Some((Precedence::Shift, Associativity::Left)),
+            Operation::BitAnd => Some((Precedence::BitAnd, Associativity::Left)),
+            Operation::BitXor => Some((Precedence::BitXor, Associativity::Left)),
+            Operation::BitOr => Some((Precedence::BitOr, Associativity::Left)),
+            Operation::Less | Operation::LessEqual | Operation::Greater | Operation::GreaterEqual => Some((Precedence::Relational, Associativity::Left)),
+            Operation::Equality | Operation::Inequality => Some((Precedence::Equality, Associativity::Left)),
+            Operation::And | Operation::Nand => Some((Precedence::LogAnd, Associativity::Left)),
+            Operation::Or | Operation::Nor => Some((Precedence::LogOr, Associativity::Left)),
+            Operation::Lambda => Some((Precedence::Lambda, Associativity::Right)),
+            Operation::Assignment | Operation::Define | Operation::Equation => Some((Precedence::Assignment, Associativity::Left)),
+            Operation::Const => Some((Precedence::Declaration, Associativity::None)),
+            _ => None,
+        }
+    }
+
+    // whether calling it twice with the same arguments gives the same result without side effects,
+    // only then may it be folded into a constant or combined with a copy of itself
+    fn is_pure(&self) -> bool {
+        !matches!(*self, Operation::Rand | Operation::RandInt | Operation::RandN | Operation::Choice | Operation::Seed | Operation::Word | Operation::Format | Operation::AngleMode)
+    }
+
+    // symbol of an operator or identifier of a function
+    fn name(&self) -> &'static str {
+        match *self {
+            Operation::Negation => "-",
+            Operation::Identity => "+",
+            Operation::Not => "not",
+            Operation::Addition => "+",
+            Operation::Subtraction => "-",
+            Operation::Multiplication => "*",
+            Operation::Division => "/",
+            Operation::Modulus => "%",
+            Operation::Assignment => "=",
+            Operation::Define => ":=",
+            Operation::Const => "const",
+            Operation::Equality => "==",
+            Operation::Inequality => "!=",
+            Operation::Less => "<",
+            Operation::LessEqual => "<=",
+            Operation::Greater => ">",
+            Operation::GreaterEqual => ">=",
+            Operation::And => "and",
+            Operation::Nand => "nand",
+            Operation::Or => "or",
+            Operation::Nor => "nor",
+            Operation::Power => "^",
+            Operation::Factorial => "!",
+            Operation::Lambda => "->",
+            Operation::Sin => "sin",
+            Operation::Cos => "cos",
+            Operation::Tan => "tan",
+            Operation::Abs => "abs",
+            Operation::Sqrt => "sqrt",
+            Operation::Radians => "radians",
+            Operation::Degrees => "degrees",
+            Operation::Ln => "ln",
+            Operation::Log2 => "log2",
+            Operation::Log10 => "log10",
+            Operation::Exp => "exp",
+            Operation::Sign => "sign",
+            Operation::Result => "result",
+            Operation::Length => "len",
+            Operation::Min => "min",
+            Operation::Max => "max",
+            Operation::Range => "range",
+            Operation::Map => "map",
+            Operation::Filter => "filter",
+            Operation::Reduce => "reduce",
+            Operation::SumOver => "sum_over",
+            Operation::Call => "call",
+            Operation::Sum => "sum",
+            Operation::Product => "prod",
+            Operation::Diff => "diff",
+            Operation::Simplify => "simplify",
+            Operation::Integrate => "integrate",
+            Operation::Index => "index",
+            Operation::Equation => "=",
+            Operation::Solve => "solve",
+            Operation::Root => "root",
+            Operation::Poly => "poly",
+            Operation::PolyDiv => "polydiv",
+            Operation::PolyGcd => "polygcd",
+            Operation::PolyDer => "polyder",
+            Operation::Roots => "roots",
+            Operation::Coeffs => "coeffs",
+            Operation::Degree => "degree",
+            Operation::Ode => "ode",
+            Operation::Minimize => "minimize",
+            Operation::Maximize => "maximize",
+            Operation::Asin => "asin",
+            Operation::Acos => "acos",
+            Operation::Atan => "atan",
+            Operation::Atan2 => "atan2",
+            Operation::Sinh => "sinh",
+            Operation::Cosh => "cosh",
+            Operation::Tanh => "tanh",
+            Operation::Asinh => "asinh",
+            Operation::Acosh => "acosh",
+            Operation::Atanh => "atanh",
+            Operation::Floor => "floor",
+            Operation::Ceil => "ceil",
+            Operation::Round => "round",
+            Operation::Trunc => "trunc",
+            Operation::Frac => "frac",
+            Operation::Hypot => "hypot",
+            Operation::Cbrt => "cbrt",
+            Operation::NthRoot => "nthroot",
+            Operation::Log => "log",
+            Operation::Gcd => "gcd",
+            Operation::Lcm => "lcm",
+            Operation::Ncr => "nCr",
+            Operation::Npr => "nPr",
+            Operation::Clamp => "clamp",
+            Operation::Lerp => "lerp",
+            Operation::Gamma => "gamma",
+            Operation::LogGamma => "lgamma",
+            Operation::Beta => "beta",
+            Operation::Digamma => "digamma",
+            Operation::NormPdf => "normpdf",
+            Operation::NormCdf => "normcdf",
+            Operation::NormInv => "norminv",
+            Operation::TPdf => "tpdf",
+            Operation::TCdf => "tcdf",
+            Operation::TInv => "tinv",
+            Operation::Chi2Pdf => "chi2pdf",
+            Operation::Chi2Cdf => "chi2cdf",
+            Operation::Chi2Inv => "chi2inv",
+            Operation::FPdf => "fpdf",
+            Operation::FCdf => "fcdf",
+            Operation::FInv => "finv",
+            Operation::BinomPdf => "binompdf",
+            Operation::BinomCdf => "binomcdf",
+            Operation::BinomInv => "binominv",
+            Operation::PoissPdf => "poisspdf",
+            Operation::PoissCdf => "poisscdf",
+            Operation::PoissInv => "poissinv",
+            Operation::ExpPdf => "exppdf",
+            Operation::ExpCdf => "expcdf",
+            Operation::ExpInv => "expinv",
+            Operation::UnifPdf => "unifpdf",
+            Operation::UnifCdf => "unifcdf",
+            Operation::UnifInv => "unifinv",
+            Operation::Erf => "erf",
+            Operation::Erfc => "erfc",
+            Operation::Rand => "rand",
+            Operation::RandInt => "randint",
+            Operation::RandN => "randn",
+            Operation::Choice => "choice",
+            Operation::Seed => "seed",
+            Operation::Pv => "pv",
+            Operation::Fv => "fv",
+            Operation::Pmt => "pmt",
+            Operation::Nper => "nper",
+            Operation::Rate => "rate",
+            Operation::Npv => "npv",
+            Operation::Irr => "irr",
+            Operation::Xirr => "xirr",
+            Operation::Amortize => "amortize",
+            Operation::IsPrime => "isprime",
+            Operation::Factor => "factor",
+            Operation::NextPrime => "nextprime",
+            Operation::ModPow => "modpow",
+            Operation::ModInv => "modinv",
+            Operation::Totient => "totient",
+            Operation::Divisors => "divisors",
+            Operation::Egcd => "egcd",
+            Operation::BitAnd => "&",
+            Operation::BitOr => "|",
+            Operation::BitXor => "xor",
+            Operation::BitNot => "~",
+            Operation::ShiftLeft => "<<",
+            Operation::ShiftRight => ">>",
+            Operation::RotateLeft => "rotl",
+            Operation::RotateRight => "rotr",
+            Operation::Word => "word",
+            Operation::Format => "format",
+            Operation::DegreeSign => "°",
+            Operation::AngleMode => "angle",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 enum Token {
     LeftParentheses,
     RightParentheses,
+    LeftBracket,
+    RightBracket,
     ArgumentSeparator,
     Operator{ parameters: u32, precedence: Precedence, associativity: Associativity, operation: Operation },
     Function{ parameters: u32, operation: Operation },
-    Operand{ value: f64, var_index: Option<usize> },
+    Operand{ value: f64 },
+    Integer{ value: u128 },
+    Variable{ name: String },
+    List{ length: u32 },
 }
 
+// longest list that range() will create
+const MAX_LIST_LENGTH: usize = 1_000_000;
+
+// most terms that sum() and prod() will evaluate
+const MAX_ITERATIONS: usize = 1_000_000;
+
+// tolerance of numeric methods when none is given
+const DEFAULT_TOLERANCE: f64 = 1e-10;
+
 struct Calculator {
-    results: Vec<f64>,
-    variables: Vec<(String, f64)>
+    results: Vec<Value>,
+    variables: HashMap<String, Value>,
+    // variables bound by lambdas while their bodies are evaluated
+    locals: Vec<(String, Value)>,
+    random: Random,
+    // word size and signedness in programmer mode
+    word: Option<WordMode>,
+    // how results are printed
+    format: Format,
+    // unit of the angles of trigonometric functions
+    angle: Angle,
+    // variables declared const, with the number of the result that declared them
+    read_only: Vec<(String, usize)>,
+    // variables defined by := as expressions, which are evaluated whenever they're used
+    formulas: Vec<(String, Expr)>,
 }
 
 fn operator(parameters: u32, precedence: Precedence, associativity: Associativity, operation: Operation) -> Result<Token, String> {
     Ok(Token::Operator{ parameters, precedence, associativity, operation })
 }
 
+// comments run from '#' or '//' to the end of the line
+fn is_comment_start(chars: &[char], pos: usize) -> bool {
+    chars[pos] == '#' || (chars[pos] == '/' && pos + 1 != chars.len() && chars[pos + 1] == '/')
+}
+
+// whether an identifier ending at a position is followed by = or :=, but not ==
+fn is_assignment(chars: &[char], pos: usize) -> bool {
+    let mut rest = chars[pos..].iter().skip_while(|ch| ch.is_whitespace());
+    match rest.next() {
+        Some('=') => rest.next() != Some(&'='),
+        Some(':') => rest.next() == Some(&'='),
+        _ => false,
+    }
+}
+
+fn strip_comments(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut output = String::new();
+    let mut pos: usize = 0;
+
+    while pos != chars.len() {
+        if is_comment_start(&chars, pos) {
+            while pos != chars.len() && chars[pos] != '\n' {
+                pos += 1;
+            }
+        }
+        else {
+            output.push(chars[pos]);
+            pos += 1;
+        }
+    }
+
+    output
+}
+
+// checks if the input has unbalanced parentheses or ends with an operator, in which case the
+// console keeps reading continuation lines before evaluating. only expressions are continued,
+// a command is always a single line
+fn is_incomplete(input: &str) -> bool {
+    if input.trim_start().starts_with(':') {
+        return false;
+    }
+    let code = strip_comments(input);
+    let mut depth: i32 = 0;
+    for ch in code.chars() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => { }
+        }
+    }
+    if depth > 0 {
+        return true;
+    }
+
+    let code = code.trim_end();
+    if let Some(ch) = code.chars().last() {
+        if "+-*/%^=<>&|~,([".contains(ch) {
+            return true;
+        }
+    }
+
+    let word: String = code.chars().rev()
+        .take_while(|ch| ch.is_alphanumeric() || *ch == '_')
+        .collect::<String>().chars().rev().collect();
+    let preceding = code[..code.len() - word.len()].chars().last();
+    if preceding.is_some_and(|ch| ch.is_alphanumeric() || ch == '_' || ch == '.') {
+        return false;
+    }
+    matches!(word.to_lowercase().as_ref(), "not" | "mod" | "and" | "nand" | "or" | "nor" | "xor")
+}
+
+// pops the operators inside of a pair of parentheses or brackets, returning the opening token
+fn close_group(stack: &mut Vec<Token>, output: &mut Vec<Token>, remove: bool) -> Result<Token, String> {
+    while let Some(t) = stack.last().cloned() {
+        if let Token::LeftParentheses | Token::LeftBracket = t {
+            if remove {
+                stack.pop();
+            }
+            return Ok(t);
+        }
+        output.push(stack.pop().unwrap());
+    }
+    Err(String::from("Missing left parentheses"))
+}
+
+fn pop_operands(operands: &mut Vec<Expr>, count: u32) -> Result<Vec<Expr>, String> {
+    if operands.len() < count as usize {
+        return Err(String::from("Insufficient operands"));
+    }
+    let start = operands.len() - count as usize;
+    Ok(operands.split_off(start))
+}
+
+// an expression of one variable as a function for the numeric methods, along with its symbolic
+// derivative when there is one
+struct Univariate<'a> {
+    calculator: &'a mut Calculator,
+    variable: &'a str,
+    expr: &'a Expr,
+    derivative: Option<Expr>,
+}
+
+impl<'a> numeric::Objective for Univariate<'a> {
+    fn value(&mut self, x: f64) -> Result<f64, String> {
+        self.calculator.evaluate_with(self.variable, Value::Number(x), self.expr)?.to_number()
+    }
+
+    fn derivative(&mut self, x: f64) -> Result<f64, String> {
+        match self.derivative {
+            Some(ref derivative) => self.calculator.evaluate_with(self.variable, Value::Number(x), derivative)?.to_number(),
+            None => numeric::central_difference(self, x),
+        }
+    }
+
+    // the sides of an equation, which is solved as their difference
+    fn scale(&mut self, x: f64) -> Result<f64, String> {
+        match *self.expr {
+            Expr::Call(Operation::Subtraction, ref sides) if sides.len() == 2 => {
+                let lhs = self.calculator.evaluate_with(self.variable, Value::Number(x), &sides[0])?.to_number()?;
+                let rhs = self.calculator.evaluate_with(self.variable, Value::Number(x), &sides[1])?.to_number()?;
+                Ok(lhs.abs().max(rhs.abs()))
+            },
+            _ => Ok(self.value(x)?.abs()),
+        }
+    }
+}
+
+fn undefined(operation: Operation, params: &[f64]) -> String {
+    let args: Vec<String> = params.iter().map(f64::to_string).collect();
+    if operation.operator_precedence().is_some() && params.len() == 2 {
+        // binary operators are written between their operands, negative ones in parentheses
+        let operand = |x: f64| if x < 0.0 { format!("({})", x) } else { x.to_string() };
+        return format!("{} {} {} is undefined", operand(params[0]), operation.name(), operand(params[1]));
+    }
+    format!("{}({}) is undefined", operation.name(), args.join(", "))
+}
+
+// an argument of a number theory function as an exact integer. words and integer literals are
+// exact as they are, while numbers beyond 2^53 may have been rounded so they're left out
+fn exact_integer(arg: &Expr, value: &Value) -> Option<i128> {
+    match (arg, value) {
+        (_, &Value::Word(word)) => word.to_i128(),
+        (&Expr::Integer(n), _) if n <= i128::MAX as u128 => Some(n as i128),
+        (&Expr::Call(Operation::Negation, ref args), _) => match args[0] {
+            Expr::Integer(n) if n <= i128::MAX as u128 => Some(-(n as i128)),
+            _ => value.to_number().ok().and_then(integer::from_f64),
+        },
+        _ => value.to_number().ok().and_then(integer::from_f64),
+    }
+}
+
+// the number theory functions of integers within 64 bits, giving a list for those that return
+// several and nothing for arguments they're undefined for
+fn number_theory(operation: Operation, n: &[i128]) -> Result<Option<Vec<i128>>, String> {
+    let result = match operation {
+        Operation::IsPrime => Some(if n[0] > 1 && integer::is_prime(n[0] as u64) { 1 } else { 0 }),
+        // every number below 2 is followed by the first prime
+        Operation::NextPrime if n[0] >= 0 => integer::next_prime(n[0].max(1) as u64).map(i128::from),
+        Operation::NextPrime => Some(2),
+        Operation::ModPow if n[1] >= 0 && n[2] > 0 => {
+            Some(integer::pow_mod(n[0].rem_euclid(n[2]) as u64, n[1] as u64, n[2] as u64).into())
+        },
+        Operation::ModInv if n[1] > 0 => match integer::mod_inverse(n[0], n[1] as u64) {
+            Some(inverse) => Some(inverse.into()),
+            None => return Err(format!("{} has no inverse modulo {}", n[0], n[1])),
+        },
+        Operation::Totient if n[0] > 0 => Some(integer::totient(n[0] as u64).into()),
+        Operation::Egcd => {
+            let (g, x, y) = integer::egcd(n[0], n[1]);
+            return Ok(Some(vec![g, x, y]));
+        },
+        Operation::Factor if n[0] > 0 => return Ok(Some(integer::factor(n[0] as u64).into_iter().map(i128::from).collect())),
+        Operation::Divisors if n[0] > 0 => return Ok(Some(integer::divisors(n[0] as u64).into_iter().map(i128::from).collect())),
+        _ => None,
+    };
+    Ok(result.map(|result| vec![result]))
+}
+
+fn gcd(a: f64, b: f64) -> f64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0.0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+// binomial coefficient, multiplied up in an order that keeps every partial product an integer
+fn choose(n: f64, r: f64) -> f64 {
+    if r > n {
+        return 0.0;
+    }
+    let r = r.min(n - r);
+    let mut result = 1.0f64;
+    let mut i = 1.0;
+    while i <= r && result.is_finite() {
+        result = result * (n - r + i) / i;
+        i += 1.0;
+    }
+    result.round()
+}
+
+fn permutations(n: f64, r: f64) -> f64 {
+    if r > n {
+        return 0.0;
+    }
+    let mut result = 1.0f64;
+    let mut i = 0.0;
+    while i < r && result.is_finite() {
+        result *= n - i;
+        i += 1.0;
+    }
+    result
+}
+
+// operands are brought into the word size first, integral numbers included
+fn word_arithmetic(mode: WordMode, operation: Operation, values: &[Value]) -> Result<Word, String> {
+    let mut words = Vec::new();
+    for value in values {
+        words.push(match *value {
+            Value::Word(word) => mode.convert(word)?,
+            ref value => mode.of_f64(value.to_number()?)?,
+        });
+    }
+    mode.apply(operation, &words)
+}
+
+fn polynomial_arithmetic(operation: Operation, values: &[Value]) -> Result<Polynomial, String> {
+    let a = values[0].to_polynomial()?;
+    Ok(match operation {
+        Operation::Identity => a,
+        Operation::Negation => a.neg(),
+        Operation::Addition => a.add(&values[1].to_polynomial()?),
+        Operation::Subtraction => a.sub(&values[1].to_polynomial()?),
+        Operation::Multiplication => a.mul(&values[1].to_polynomial()?),
+        Operation::Division => {
+            let (quotient, remainder) = a.div_rem(&values[1].to_polynomial()?)?;
+            if !remainder.is_zero() {
+                return Err(String::from("Polynomial division leaves a remainder, use polydiv"));
+            }
+            quotient
+        },
+        _ => a.pow(values[1].to_number()?)?,
+    })
+}
+
 impl Calculator {
 
-    fn get_number(&self, pos: &mut usize, chars: &Vec<char>) -> Token {
+    fn get_number(&self, pos: &mut usize, chars: &[char]) -> Result<Token, String> {
+        // integers written in hexadecimal, octal or binary
+        let radix = match chars.get(*pos + 1) {
+            Some('x') | Some('X') => 16,
+            Some('o') | Some('O') => 8,
+            Some('b') | Some('B') => 2,
+            _ => 10,
+        };
+        if chars[*pos] == '0' && radix != 10 && chars.get(*pos + 2).is_some_and(|ch| ch.is_digit(radix)) {
+            *pos += 2;
+            let mut value: u128 = 0;
+            while *pos != chars.len() && (chars[*pos].is_digit(radix) || chars[*pos] == '_') {
+                if let Some(digit) = chars[*pos].to_digit(radix) {
+                    value = value.checked_mul(radix as u128).and_then(|value| value.checked_add(digit as u128))
+                        .ok_or_else(|| String::from("Integer doesn't fit in 128 bits"))?;
+                }
+                *pos += 1;
+            }
+            return Ok(self.integer(value));
+        }
+
         let mut found_dot = false;
         let mut digits = chars[*pos].to_string();
 
@@ -104,58 +801,87 @@ impl Calculator {
 
             let ch = chars[*pos];
 
-            if ch.is_digit(10) {
+            if ch.is_ascii_digit() {
                 digits.push(ch);
             }
             else {
-                if ch == '.' {
-                    if !found_dot {
-                        found_dot = true;
-                        digits.push(ch);
-                        continue;
-                    }
+                if ch == '.' && !found_dot {
+                    found_dot = true;
+                    digits.push(ch);
+                    continue;
                 }
                 break;
             }
         }
 
-        Token::Operand{ value: digits.parse().unwrap(), var_index: None }
+        // an exponent like the e5 in 2e5 or the E-20 in 1E-20, while an e followed by anything else
+        // is Euler's number multiplying the mantissa
+        let sign = matches!(chars.get(*pos + 1), Some('+') | Some('-')) as usize;
+        let exponent = matches!(chars.get(*pos), Some('e') | Some('E'))
+            && chars.get(*pos + 1 + sign).is_some_and(|ch| ch.is_ascii_digit());
+        if exponent {
+            digits.push('e');
+            digits.extend(&chars[*pos + 1..*pos + 1 + sign]);
+            *pos += 1 + sign;
+            while *pos != chars.len() && chars[*pos].is_ascii_digit() {
+                digits.push(chars[*pos]);
+                *pos += 1;
+            }
+        }
+
+        if !found_dot && !exponent {
+            match digits.parse() {
+                Ok(value) => return Ok(self.integer(value)),
+                Err(_) if self.word.is_some() => return Err(String::from("Integer doesn't fit in 128 bits")),
+                Err(_) => { },
+            }
+        }
+        Ok(Token::Operand{ value: digits.parse().unwrap() })
+    }
+
+    // programmer mode keeps integers exact beyond what a float can hold. otherwise they're only
+    // kept exact past 2^53 for the number theory functions, and read as floats everywhere else
+    fn integer(&self, value: u128) -> Token {
+        match self.word {
+            None if value <= 1 << 53 => Token::Operand{ value: value as f64 },
+            _ => Token::Integer{ value },
+        }
     }
 
-    fn get_identifier(&mut self, pos: &mut usize, chars: &Vec<char>) -> Result<Token, String> {
+    fn get_identifier(&self, pos: &mut usize, chars: &[char]) -> Result<Token, String> {
         let mut ident = chars[*pos].to_string();
 
-        let mut is_func = false;
         loop {
             *pos += 1;
             if *pos == chars.len() {
                 break;
             }
             let ch = chars[*pos];
-            if ch.is_alphabetic() || ch.is_digit(10) || ch == '_' {
+            if ch.is_alphabetic() || ch.is_ascii_digit() || ch == '_' {
                 ident.push(ch);
                 continue;
             }
-            if ch == '(' {
-                is_func = true;
-            }
             break;
         }
 
+        if let Some(constant) = constants::lookup(&ident) {
+            // the constant is read as its value, so assigning to it would silently do nothing
+            if is_assignment(chars, *pos) {
+                return Err(format!("Cannot assign to {}, it's a built-in constant", ident));
+            }
+            return Ok(Token::Operand{ value: constant.value });
+        }
+
         let identifier = ident.to_lowercase();
         match identifier.as_ref() {
-            "pi"    => Ok(Token::Operand{ value: std::f64::consts::PI, var_index: None }),
-            "tau"   => Ok(Token::Operand{ value: std::f64::consts::PI * 2.0, var_index: None }),
-            "e"     => Ok(Token::Operand{ value: std::f64::consts::E, var_index: None }),
-            "true"  => Ok(Token::Operand{ value: 1.0, var_index: None }),
-            "false" => Ok(Token::Operand{ value: 0.0, var_index: None }),
-
             "not"  => operator(1, Precedence::Unary,  Associativity::None, Operation::Not),
             "mod"  => operator(2, Precedence::Multiplicative, Associativity::Left, Operation::Modulus),
             "and"  => operator(2, Precedence::LogAnd, Associativity::Left, Operation::And),
             "nand" => operator(2, Precedence::LogAnd, Associativity::Left, Operation::Nand),
             "or"   => operator(2, Precedence::LogOr, Associativity::Left, Operation::Or),
             "nor"  => operator(2, Precedence::LogOr, Associativity::Left, Operation::Nor),
+            "xor"  => operator(2, Precedence::BitXor, Associativity::Left, Operation::BitXor),
+            "const" => operator(1, Precedence::Declaration, Associativity::None, Operation::Const),
 
             "sin"     => Ok(Token::Function{ parameters: 1, operation: Operation::Sin }),
             "cos"     => Ok(Token::Function{ parameters: 1, operation: Operation::Cos }),
@@ -170,45 +896,148 @@ impl Calculator {
             "log10"   => Ok(Token::Function{ parameters: 1, operation: Operation::Log10 }),
             "exp"     => Ok(Token::Function{ parameters: 1, operation: Operation::Exp }),
             "sign"    => Ok(Token::Function{ parameters: 1, operation: Operation::Sign }),
+            "asin"    => Ok(Token::Function{ parameters: 1, operation: Operation::Asin }),
+            "acos"    => Ok(Token::Function{ parameters: 1, operation: Operation::Acos }),
+            "atan"    => Ok(Token::Function{ parameters: 1, operation: Operation::Atan }),
+            "atan2"   => Ok(Token::Function{ parameters: 2, operation: Operation::Atan2 }),
+            "sinh"    => Ok(Token::Function{ parameters: 1, operation: Operation::Sinh }),
+            "cosh"    => Ok(Token::Function{ parameters: 1, operation: Operation::Cosh }),
+            "tanh"    => Ok(Token::Function{ parameters: 1, operation: Operation::Tanh }),
+            "asinh"   => Ok(Token::Function{ parameters: 1, operation: Operation::Asinh }),
+            "acosh"   => Ok(Token::Function{ parameters: 1, operation: Operation::Acosh }),
+            "atanh"   => Ok(Token::Function{ parameters: 1, operation: Operation::Atanh }),
+            "floor"   => Ok(Token::Function{ parameters: 1, operation: Operation::Floor }),
+            "ceil"    => Ok(Token::Function{ parameters: 1, operation: Operation::Ceil }),
+            "round"   => Ok(Token::Function{ parameters: 1, operation: Operation::Round }),
+            "trunc"   => Ok(Token::Function{ parameters: 1, operation: Operation::Trunc }),
+            "frac"    => Ok(Token::Function{ parameters: 1, operation: Operation::Frac }),
+            "hypot"   => Ok(Token::Function{ parameters: 2, operation: Operation::Hypot }),
+            "cbrt"    => Ok(Token::Function{ parameters: 1, operation: Operation::Cbrt }),
+            "nthroot" => Ok(Token::Function{ parameters: 2, operation: Operation::NthRoot }),
+            "log"     => Ok(Token::Function{ parameters: 1, operation: Operation::Log }),
+            "gcd"     => Ok(Token::Function{ parameters: 2, operation: Operation::Gcd }),
+            "lcm"     => Ok(Token::Function{ parameters: 2, operation: Operation::Lcm }),
+            "ncr"     => Ok(Token::Function{ parameters: 2, operation: Operation::Ncr }),
+            "npr"     => Ok(Token::Function{ parameters: 2, operation: Operation::Npr }),
+            "clamp"   => Ok(Token::Function{ parameters: 3, operation: Operation::Clamp }),
+            "lerp"    => Ok(Token::Function{ parameters: 3, operation: Operation::Lerp }),
+            "gamma"   => Ok(Token::Function{ parameters: 1, operation: Operation::Gamma }),
+            "lgamma"  => Ok(Token::Function{ parameters: 1, operation: Operation::LogGamma }),
+            "beta"    => Ok(Token::Function{ parameters: 2, operation: Operation::Beta }),
+            "digamma" => Ok(Token::Function{ parameters: 1, operation: Operation::Digamma }),
+
+            "normpdf"  => Ok(Token::Function{ parameters: 1, operation: Operation::NormPdf }),
+            "normcdf"  => Ok(Token::Function{ parameters: 1, operation: Operation::NormCdf }),
+            "norminv"  => Ok(Token::Function{ parameters: 1, operation: Operation::NormInv }),
+            "tpdf"     => Ok(Token::Function{ parameters: 2, operation: Operation::TPdf }),
+            "tcdf"     => Ok(Token::Function{ parameters: 2, operation: Operation::TCdf }),
+            "tinv"     => Ok(Token::Function{ parameters: 2, operation: Operation::TInv }),
+            "chi2pdf"  => Ok(Token::Function{ parameters: 2, operation: Operation::Chi2Pdf }),
+            "chi2cdf"  => Ok(Token::Function{ parameters: 2, operation: Operation::Chi2Cdf }),
+            "chi2inv"  => Ok(Token::Function{ parameters: 2, operation: Operation::Chi2Inv }),
+            "fpdf"     => Ok(Token::Function{ parameters: 3, operation: Operation::FPdf }),
+            "fcdf"     => Ok(Token::Function{ parameters: 3, operation: Operation::FCdf }),
+            "finv"     => Ok(Token::Function{ parameters: 3, operation: Operation::FInv }),
+            "binompdf" => Ok(Token::Function{ parameters: 3, operation: Operation::BinomPdf }),
+            "binomcdf" => Ok(Token::Function{ parameters: 3, operation: Operation::BinomCdf }),
+            "binominv" => Ok(Token::Function{ parameters: 3, operation: Operation::BinomInv }),
+            "poisspdf" => Ok(Token::Function{ parameters: 2, operation: Operation::PoissPdf }),
+            "poisscdf" => Ok(Token::Function{ parameters: 2, operation: Operation::PoissCdf }),
+            "poissinv" => Ok(Token::Function{ parameters: 2, operation: Operation::PoissInv }),
+            "exppdf"   => Ok(Token::Function{ parameters: 1, operation: Operation::ExpPdf }),
+            "expcdf"   => Ok(Token::Function{ parameters: 1, operation: Operation::ExpCdf }),
+            "expinv"   => Ok(Token::Function{ parameters: 1, operation: Operation::ExpInv }),
+            "unifpdf"  => Ok(Token::Function{ parameters: 1, operation: Operation::UnifPdf }),
+            "unifcdf"  => Ok(Token::Function{ parameters: 1, operation: Operation::UnifCdf }),
+            "unifinv"  => Ok(Token::Function{ parameters: 1, operation: Operation::UnifInv }),
+            "erf"      => Ok(Token::Function{ parameters: 1, operation: Operation::Erf }),
+            "erfc"     => Ok(Token::Function{ parameters: 1, operation: Operation::Erfc }),
+
+            "rand"     => Ok(Token::Function{ parameters: 0, operation: Operation::Rand }),
+            "randint"  => Ok(Token::Function{ parameters: 2, operation: Operation::RandInt }),
+            "randn"    => Ok(Token::Function{ parameters: 0, operation: Operation::RandN }),
+            "choice"   => Ok(Token::Function{ parameters: 1, operation: Operation::Choice }),
+            "seed"     => Ok(Token::Function{ parameters: 1, operation: Operation::Seed }),
+
+            "pv"       => Ok(Token::Function{ parameters: 3, operation: Operation::Pv }),
+            "fv"       => Ok(Token::Function{ parameters: 3, operation: Operation::Fv }),
+            "pmt"      => Ok(Token::Function{ parameters: 3, operation: Operation::Pmt }),
+            "nper"     => Ok(Token::Function{ parameters: 3, operation: Operation::Nper }),
+            "rate"     => Ok(Token::Function{ parameters: 3, operation: Operation::Rate }),
+            "npv"      => Ok(Token::Function{ parameters: 2, operation: Operation::Npv }),
+            "irr"      => Ok(Token::Function{ parameters: 1, operation: Operation::Irr }),
+            "xirr"     => Ok(Token::Function{ parameters: 2, operation: Operation::Xirr }),
+            "amortize" => Ok(Token::Function{ parameters: 3, operation: Operation::Amortize }),
+
+            "isprime"   => Ok(Token::Function{ parameters: 1, operation: Operation::IsPrime }),
+            "factor"    => Ok(Token::Function{ parameters: 1, operation: Operation::Factor }),
+            "nextprime" => Ok(Token::Function{ parameters: 1, operation: Operation::NextPrime }),
+            "modpow"    => Ok(Token::Function{ parameters: 3, operation: Operation::ModPow }),
+            "modinv"    => Ok(Token::Function{ parameters: 2, operation: Operation::ModInv }),
+            "totient"   => Ok(Token::Function{ parameters: 1, operation: Operation::Totient }),
+            "divisors"  => Ok(Token::Function{ parameters: 1, operation: Operation::Divisors }),
+            "egcd"      => Ok(Token::Function{ parameters: 2, operation: Operation::Egcd }),
+
+            "rotl"      => Ok(Token::Function{ parameters: 2, operation: Operation::RotateLeft }),
+            "rotr"      => Ok(Token::Function{ parameters: 2, operation: Operation::RotateRight }),
+            "word"      => Ok(Token::Function{ parameters: 1, operation: Operation::Word }),
+            "format"    => Ok(Token::Function{ parameters: 1, operation: Operation::Format }),
+            "angle"     => Ok(Token::Function{ parameters: 1, operation: Operation::AngleMode }),
 
             "min"  => Ok(Token::Function{ parameters: 2, operation: Operation::Min }),
             "max"  => Ok(Token::Function{ parameters: 2, operation: Operation::Max }),
             "pow"  => Ok(Token::Function{ parameters: 2, operation: Operation::Power }),
 
-            _ => {
-                if is_func {
-                    Err(format!("Unknown function '{}'", ident))
-                }
-                else {
-                    if let Some(index) = self.variables.iter().position(|&(ref name, ..)| identifier == *name) {
-                        Ok(Token::Operand{ value: self.variables[index].1, var_index: Some(index) })
-                    }
-                    else {
-                        let new_var = Token::Operand{ value: 0.0, var_index: Some(self.variables.len()) };
-                        self.variables.push((identifier, 0.0));
-                        Ok(new_var)
-                    }
-                }
-            }
+            "len"      => Ok(Token::Function{ parameters: 1, operation: Operation::Length }),
+            "range"    => Ok(Token::Function{ parameters: 2, operation: Operation::Range }),
+            "map"      => Ok(Token::Function{ parameters: 2, operation: Operation::Map }),
+            "filter"   => Ok(Token::Function{ parameters: 2, operation: Operation::Filter }),
+            "reduce"   => Ok(Token::Function{ parameters: 2, operation: Operation::Reduce }),
+            "sum_over" => Ok(Token::Function{ parameters: 2, operation: Operation::SumOver }),
+            "sum"      => Ok(Token::Function{ parameters: 4, operation: Operation::Sum }),
+            "prod"     => Ok(Token::Function{ parameters: 4, operation: Operation::Product }),
+            "diff"     => Ok(Token::Function{ parameters: 2, operation: Operation::Diff }),
+            "simplify" => Ok(Token::Function{ parameters: 2, operation: Operation::Simplify }),
+            "integrate" => Ok(Token::Function{ parameters: 4, operation: Operation::Integrate }),
+            "solve"    => Ok(Token::Function{ parameters: 2, operation: Operation::Solve }),
+            "root"     => Ok(Token::Function{ parameters: 3, operation: Operation::Root }),
+            "poly"     => Ok(Token::Function{ parameters: 1, operation: Operation::Poly }),
+            "polydiv"  => Ok(Token::Function{ parameters: 2, operation: Operation::PolyDiv }),
+            "polygcd"  => Ok(Token::Function{ parameters: 2, operation: Operation::PolyGcd }),
+            "polyder"  => Ok(Token::Function{ parameters: 1, operation: Operation::PolyDer }),
+            "roots"    => Ok(Token::Function{ parameters: 1, operation: Operation::Roots }),
+            "coeffs"   => Ok(Token::Function{ parameters: 1, operation: Operation::Coeffs }),
+            "degree"   => Ok(Token::Function{ parameters: 1, operation: Operation::Degree }),
+            "ode"      => Ok(Token::Function{ parameters: 4, operation: Operation::Ode }),
+            "minimize" => Ok(Token::Function{ parameters: 3, operation: Operation::Minimize }),
+            "maximize" => Ok(Token::Function{ parameters: 3, operation: Operation::Maximize }),
+
+            _ => Ok(Token::Variable{ name: identifier }),
         }
     }
 
-    fn tokenize(&mut self, expression: &str) -> Result<Vec<Token>, String> {
-        if expression.len() == 0 {
-            return Err(String::from("Expression is empty"));
-        }
-
+    fn tokenize(&self, expression: &str) -> Result<Vec<Token>, String> {
         let chars: Vec<char> = expression.chars().collect();
         let mut output: Vec<Token> = Vec::new();
         let mut pos: usize = 0;
 
         loop {
-            // ignore whitespace
-            while pos != expression.len() && chars[pos].is_whitespace() {
-                pos += 1;
+            // ignore whitespace and comments
+            while pos != chars.len() {
+                if chars[pos].is_whitespace() {
+                    pos += 1;
+                }
+                else if is_comment_start(&chars, pos) {
+                    while pos != chars.len() && chars[pos] != '\n' {
+                        pos += 1;
+                    }
+                }
+                else {
+                    break;
+                }
             }
 
-            if pos == expression.len() {
+            if pos == chars.len() {
                 break;
             }
             let ch = chars[pos];
@@ -217,25 +1046,55 @@ impl Calculator {
             if let Some(token) = match chars[pos] {
                 '(' => Some(Token::LeftParentheses),
                 ')' => Some(Token::RightParentheses),
+                ']' => Some(Token::RightBracket),
                 ',' => Some(Token::ArgumentSeparator),
                 '/' => Some(Token::Operator{ parameters: 2, precedence: Precedence::Multiplicative, associativity: Associativity::Left, operation: Operation::Division }),
                 '%' => Some(Token::Operator{ parameters: 2, precedence: Precedence::Multiplicative, associativity: Associativity::Left, operation: Operation::Modulus }),
                 '^' => Some(Token::Operator{ parameters: 2, precedence: Precedence::Power, associativity: Associativity::Right, operation: Operation::Power }),
+                '°' => Some(Token::Operator{ parameters: 1, precedence: Precedence::Postfix, associativity: Associativity::None, operation: Operation::DegreeSign }),
+                '~' => Some(Token::Operator{ parameters: 1, precedence: Precedence::Unary, associativity: Associativity::None, operation: Operation::BitNot }),
                 _ => None
             } {
                 output.push(token);
                 pos += 1;
             }
 
+            else if ch == '[' {
+                // brackets following an operand index into it
+                match output.last() {
+                    Some(&Token::Operand{ .. }) |
+                    Some(&Token::Integer{ .. }) |
+                    Some(&Token::Variable{ .. }) |
+                    Some(&Token::RightParentheses) |
+                    Some(&Token::RightBracket) => {
+                        output.push(Token::Function{ parameters: 2, operation: Operation::Index });
+                    },
+                    _ => { }
+                }
+                output.push(Token::LeftBracket);
+                pos += 1;
+            }
+
             // numbers
-            else if ch.is_digit(10) {
-                output.push(self.get_number(&mut pos, &chars));
+            else if ch.is_ascii_digit() {
+                output.push(self.get_number(&mut pos, &chars)?);
+
+                // a number directly followed by an identifier or parentheses multiplies it, as in 2x
+                if pos != chars.len() && (chars[pos].is_alphabetic() || chars[pos] == '(') {
+                    output.push(operator(2, Precedence::Multiplicative, Associativity::Left, Operation::Multiplication).unwrap());
+                }
             }
 
             // identifiers
             else if ch.is_alphabetic() {
                 let token = self.get_identifier(&mut pos, &chars)?;
+
+                // a variable followed by parentheses calls the function stored in it
+                let is_call = matches!(token, Token::Variable{ .. }) && pos != chars.len() && chars[pos] == '(';
                 output.push(token);
+                if is_call {
+                    output.push(Token::Function{ parameters: 0, operation: Operation::Call });
+                }
             }
 
             else if ch == '*' {
@@ -253,7 +1112,10 @@ impl Calculator {
             else if ch == '+' {
                 match output.last() {
                     Some(&Token::Operand{ .. }) |
+                    Some(&Token::Integer{ .. }) |
+                    Some(&Token::Variable{ .. }) |
                     Some(&Token::RightParentheses) |
+                    Some(&Token::RightBracket) |
                     Some(&Token::Operator{ precedence: Precedence::Postfix, .. }) => {
                         output.push(operator(2, Precedence::Additive, Associativity::Left, Operation::Addition).unwrap());
                     },
@@ -264,10 +1126,18 @@ impl Calculator {
                 pos += 1;
             }
 
+            else if ch == '-' && pos + 1 != chars.len() && chars[pos + 1] == '>' {
+                output.push(operator(2, Precedence::Lambda, Associativity::Right, Operation::Lambda).unwrap());
+                pos += 2;
+            }
+
             else if ch == '-' {
                 match output.last() {
                     Some(&Token::Operand{ .. }) |
+                    Some(&Token::Integer{ .. }) |
+                    Some(&Token::Variable{ .. }) |
                     Some(&Token::RightParentheses) |
+                    Some(&Token::RightBracket) |
                     Some(&Token::Operator{ precedence: Precedence::Postfix, .. }) => {
                         output.push(operator(2, Precedence::Additive, Associativity::Left, Operation::Subtraction).unwrap());
                     },
@@ -289,6 +1159,11 @@ impl Calculator {
                 }
             }
 
+            else if ch == '>' && pos + 1 != chars.len() && chars[pos + 1] == '>' {
+                output.push(operator(2, Precedence::Shift, Associativity::Left, Operation::ShiftRight).unwrap());
+                pos += 2;
+            }
+
             else if ch == '>' {
                 pos += 1;
                 if pos == chars.len() || (pos != chars.len() && chars[pos] != '=') {
@@ -300,6 +1175,11 @@ impl Calculator {
                 }
             }
 
+            else if ch == '<' && pos + 1 != chars.len() && chars[pos + 1] == '<' {
+                output.push(operator(2, Precedence::Shift, Associativity::Left, Operation::ShiftLeft).unwrap());
+                pos += 2;
+            }
+
             else if ch == '<' {
                 pos += 1;
                 if pos == chars.len() || (pos != chars.len() && chars[pos] != '=') {
@@ -311,6 +1191,11 @@ impl Calculator {
                 }
             }
 
+            else if ch == ':' && pos + 1 != chars.len() && chars[pos + 1] == '=' {
+                output.push(operator(2, Precedence::Assignment, Associativity::Left, Operation::Define).unwrap());
+                pos += 2;
+            }
+
             else if ch == '=' {
                 pos += 1;
                 if pos == chars.len() || (pos != chars.len() && chars[pos] != '=') {
@@ -332,11 +1217,25 @@ impl Calculator {
 		        pos += 2;
 		    }
 
+            else if ch == '&' {
+                output.push(operator(2, Precedence::BitAnd, Associativity::Left, Operation::BitAnd).unwrap());
+                pos += 1;
+            }
+
+            else if ch == '|' {
+                output.push(operator(2, Precedence::BitOr, Associativity::Left, Operation::BitOr).unwrap());
+                pos += 1;
+            }
+
             else {
                 return Err(format!("Invalid character '{}'", ch));
             }
         }
 
+        if output.is_empty() {
+            return Err(String::from("Expression is empty"));
+        }
+
         Ok(output)
     }
 
@@ -345,40 +1244,64 @@ impl Calculator {
         let mut stack: Vec<Token> = Vec::new();
         let mut output: Vec<Token> = Vec::new();
 
+        // number of arguments found inside each of the open parentheses and brackets
+        let mut arguments: Vec<u32> = Vec::new();
+
         for token in tokens {
+            if let Some(count) = arguments.last_mut() {
+                match token {
+                    Token::RightParentheses | Token::RightBracket => { },
+                    _ => if *count == 0 { *count = 1 }
+                }
+            }
+
             match token {
-                Token::Operand{ .. } => output.push(token),
+                Token::Operand{ .. } | Token::Integer{ .. } | Token::Variable{ .. } => output.push(token),
 
                 Token::ArgumentSeparator => {
-                    while let Some(t) = stack.last().cloned() {
-                        if let Token::LeftParentheses = t { break; }
-                        else { output.push(stack.pop().unwrap()); }
-                    }
-                    if stack.is_empty() {
-                        return Err(String::from("Missing left parentheses"));
-                    }
+                    close_group(&mut stack, &mut output, false)?;
+                    *arguments.last_mut().unwrap() += 1;
                 },
 
-                Token::Function{ .. } | Token::LeftParentheses => stack.push(token),
+                Token::Function{ .. } => stack.push(token),
+
+                Token::LeftParentheses | Token::LeftBracket => {
+                    stack.push(token);
+                    arguments.push(0);
+                },
 
                 Token::RightParentheses => {
-                    while let Some(t) = stack.last().cloned() {
-                        if let Token::LeftParentheses = t { break; }
-                        else { output.push(stack.pop().unwrap()); }
+                    if let Token::LeftBracket = close_group(&mut stack, &mut output, true)? {
+                        return Err(String::from("Mismatched brackets"));
+                    }
+                    let count = arguments.pop().unwrap();
+
+                    // check if parentheses followed a function
+                    if let Some(Token::Function{ operation, .. }) = stack.last().cloned() {
+                        stack.pop();
+                        output.push(Token::Function{ parameters: count, operation });
                     }
-                    if stack.is_empty() {
-                        return Err(String::from("Missing left parentheses"));
+                    else if count == 0 {
+                        return Err(String::from("Empty parentheses"));
                     }
+                    else if count > 1 {
+                        output.push(Token::List{ length: count });
+                    }
+                },
 
-                    // pop the left parentheses
-                    stack.pop();
+                Token::RightBracket => {
+                    if let Token::LeftParentheses = close_group(&mut stack, &mut output, true)? {
+                        return Err(String::from("Mismatched brackets"));
+                    }
+                    let count = arguments.pop().unwrap();
 
-                    // check if parentheses followed a function
-                    if let Some(last) = stack.last().cloned() {
-                        if let Token::Function{ .. } = last {
-                            stack.pop();
-                            output.push(last);
-                        }
+                    // check if brackets index into the operand before them
+                    if let Some(Token::Function{ operation: Operation::Index, .. }) = stack.last() {
+                        stack.pop();
+                        output.push(Token::Function{ parameters: count + 1, operation: Operation::Index });
+                    }
+                    else {
+                        output.push(Token::List{ length: count });
                     }
                 },
 
@@ -392,110 +1315,87 @@ impl Calculator {
                         }
                     }
                     stack.push(token);
-                }
+                },
+
+                Token::List{ .. } => { }
             }
         }
 
-        while let Some(stack_token) = stack.last().cloned() {
-            if let Token::RightParentheses = stack_token {
+        while let Some(stack_token) = stack.pop() {
+            if let Token::LeftParentheses | Token::LeftBracket = stack_token {
                 return Err(String::from("Missing right parentheses"));
             }
             output.push(stack_token);
-            stack.pop();
         }
 
         Ok(output)
     }
 
-    fn evaluate_rpn(&mut self, tokens: Vec<Token>) -> Result<f64, String> {
+    fn build(&self, tokens: Vec<Token>) -> Result<Expr, String> {
 
-        let mut params : Vec<f64> = Vec::new();
-        let mut operands : Vec<Token> = Vec::new();
-        let mut var_indexes: Vec<Option<usize>> = Vec::new();
+        let mut operands: Vec<Expr> = Vec::new();
 
         for token in tokens {
             match token {
-                Token::Operand{ .. } => operands.push(token),
-                Token::Function{ parameters, operation } |
-                Token::Operator{ parameters, operation, .. } => {
+                Token::Operand{ value } => operands.push(Expr::Number(value)),
+                Token::Integer{ value } => operands.push(Expr::Integer(value)),
+                Token::Variable{ name } => operands.push(Expr::Variable(name)),
 
-                    if operands.len() < parameters as usize {
-                        return Err(String::from("Insufficient operands"));
-                    }
+                Token::List{ length } => {
+                    let items = pop_operands(&mut operands, length)?;
+                    operands.push(Expr::List(items));
+                },
 
-                    params.clear();
-                    var_indexes.clear();
-                    for _ in 0..parameters {
-                        let t = operands.pop().unwrap();
-                        match t {
-                            Token::Operand{ value, var_index } => {
-                                params.insert(0, value);
-                                var_indexes.insert(0, var_index);
-                            }
-                            _ => return Err(String::from("Invalid operand"))
+                Token::Function{ parameters, operation } => {
+                    let (min, max) = operation.arity();
+                    if parameters < min || parameters > max {
+                        return Err(if min == max {
+                            format!("Expected {} arguments but found {}", min, parameters)
                         }
+                        else {
+                            format!("Expected {} to {} arguments but found {}", min, max, parameters)
+                        });
                     }
 
-                    let result = match operation {
-                        Operation::Identity => params[0],
-                        Operation::Negation => params[0] * -1.0,
-                        Operation::Not => if params[0] == 0.0 { 1.0 } else { 0.0 },
-                        Operation::Addition => params[0] + params[1],
-                        Operation::Subtraction => params[0] - params[1],
-                        Operation::Multiplication => params[0] * params[1],
-                        Operation::Division => params[0] / params[1],
-                        Operation::Modulus => params[0] % params[1],
-                        Operation::Equality => if params[0] == params[1] { 1.0 } else { 0.0 },
-                        Operation::Inequality => if params[0] != params[1] { 1.0 } else { 0.0 },
-                        Operation::Less => if params[0] < params[1] { 1.0 } else { 0.0 },
-                        Operation::LessEqual => if params[0] <= params[1] { 1.0 } else { 0.0 },
-                        Operation::Greater => if params[0] > params[1] { 1.0 } else { 0.0 },
-                        Operation::GreaterEqual => if params[0] >= params[1] { 1.0 } else { 0.0 },
-                        Operation::And => if params[0] != 0.0 && params[1] != 0.0 { 1.0 } else { 0.0 },
-                        Operation::Nand => if params[0] != 0.0 && params[1] != 0.0 { 0.0 } else { 1.0 },
-                        Operation::Or => if params[0] != 0.0 || params[1] != 0.0 { 1.0 } else { 0.0 },
-                        Operation::Nor => if params[0] != 0.0 || params[1] != 0.0 { 0.0 } else { 1.0 },
-                        Operation::Power => params[0].powf(params[1]),
-                        Operation::Sin => params[0].sin(),
-                        Operation::Cos => params[0].cos(),
-                        Operation::Tan => params[0].tan(),
-                        Operation::Abs => params[0].abs(),
-                        Operation::Sqrt => params[0].sqrt(),
-                        Operation::Radians => params[0].to_radians(),
-                        Operation::Degrees => params[0].to_degrees(),
-                        Operation::Min => f64::min(params[0], params[1]),
-                        Operation::Max => f64::max(params[0], params[1]),
-                        Operation::Result => {
-                            let index = params[0] as usize;
-                            if index < 1 || index > self.results.len() {
-                                return Err(String::from("Index out of range"));
-                            }
-                            self.results[index - 1]
+                    // calls also take the function being called
+                    let count = match operation {
+                        Operation::Call => parameters + 1,
+                        _ => parameters
+                    };
+                    let mut args = pop_operands(&mut operands, count)?;
+                    if let Some((variable, _)) = operation.binding() {
+                        // optimization can bind a list of variables
+                        let bindable = |arg: &Expr| match operation {
+                            Operation::Minimize | Operation::Maximize => arg.parameter_names().is_ok(),
+                            _ => arg.as_variable().is_some(),
+                        };
+                        if args.get(variable).is_some_and(|arg| !bindable(arg)) {
+                            return Err(String::from("Expected a variable to bind"));
                         }
-                        Operation::Ln => params[0].ln(),
-                        Operation::Log2 => params[0].log2(),
-                        Operation::Log10 => params[0].log10(),
-                        Operation::Exp => params[0].exp(),
-                        Operation::Sign => params[0].signum(),
-                        Operation::Factorial => {
-                            let mut x = params[0] as i64;
-                            let mut y = x;
-                            for i in 1..y {
-                                x *= i;
-                            }
-                            x as f64
+                    }
+
+                    // the equation being solved may look like an assignment
+                    if let (Operation::Solve, Some(Expr::Call(Operation::Assignment, sides))) = (operation, args.first()) {
+                        args[0] = Expr::Call(Operation::Equation, sides.clone());
+                    }
+                    operands.push(Expr::Call(operation, args));
+                },
+
+                Token::Operator{ parameters, operation, .. } => {
+                    let args = pop_operands(&mut operands, parameters)?;
+                    let operation = match operation {
+                        // assigning to anything but a variable is an equation, which solve() accepts
+                        Operation::Assignment if args[0].as_variable().is_none() => Operation::Equation,
+                        Operation::Define if args[0].as_variable().is_none() => return Err(String::from("Expected a variable before :=")),
+                        Operation::Lambda => {
+                            args[0].parameter_names()?;
+                            operation
                         },
-                        Operation::Assignment => {
-                            match var_indexes[0] {
-                                Some(index) => self.variables[index].1 = params[1],
-                                _=> return Err(String::from("Assignment to non-variable"))
-                            }
-                            params[1]
-                        }
+                        _ => operation
                     };
-
-                    operands.push(Token::Operand{ value: result, var_index: None });
+                    operands.push(Expr::Call(operation, args));
                 },
+
                 _ => { }
             }
         }
@@ -504,82 +1404,1216 @@ impl Calculator {
             return Err(String::from("Too many operands"))
         }
 
-        match operands.first() {
-            Some(&Token::Operand{ value, .. }) => Ok(value),
-            _ => Err(String::from("Insufficient operands"))
+        operands.pop().ok_or_else(|| String::from("Insufficient operands"))
+    }
+
+    fn get_variable(&self, name: &str) -> Option<Value> {
+        if let Some((_, value)) = self.locals.iter().rev().find(|(local, ..)| local == name) {
+            return Some(value.clone());
+        }
+        self.variables.get(name).cloned()
+    }
+
+    // fails for variables declared const, unless a lambda parameter of the same name hides them
+    fn check_writable(&self, name: &str) -> Result<(), String> {
+        if self.locals.iter().any(|(local, ..)| local == name) {
+            return Ok(());
+        }
+        match self.read_only.iter().find(|(variable, ..)| variable == name) {
+            Some((_, result)) => Err(format!("Cannot assign to {}, it was declared const in result [{}]", name, result)),
+            None => Ok(()),
         }
     }
 
-    fn evaluate_expression(&mut self, input: &str) -> Result<f64, String> {
-        let tokens = self.tokenize(input)?;
-        let rpn_tokens = self.parse(tokens)?;
-        let result = self.evaluate_rpn(rpn_tokens)?;
-        self.results.push(result);
-        Ok(result)
+    fn set_variable(&mut self, name: &str, value: Value) {
+        if let Some(local) = self.locals.iter_mut().rev().find(|(local, ..)| local == name) {
+            local.1 = value;
+        }
+        else {
+            // a value replaces the formula the variable had
+            self.formulas.retain(|(formula, ..)| formula != name);
+            self.variables.insert(String::from(name), value);
+        }
     }
 
-    fn new() -> Calculator {
-        let results = Vec::new();
-        let variables = Vec::new();
-        Calculator{ results, variables }
+    // names of the variables and formulas in alphabetical order
+    fn variable_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.variables.keys().cloned()
+            .chain(self.formulas.iter().map(|(formula, ..)| formula.clone()))
+            .collect();
+        names.sort();
+        names
     }
-}
 
-fn main() {
-    let args: Vec<_> = std::env::args().collect();
+    // every variable and formula with its current value
+    fn list_variables(&mut self) -> Vec<(String, Result<Value, String>)> {
+        let mut list = Vec::new();
+        for name in self.variable_names() {
+            let value = self.evaluate_tree(&Expr::Variable(name.clone()));
+            list.push((name, value));
+        }
+        list
+    }
 
-    let mut calculator = Calculator::new();
+    fn is_defined(&self, name: &str) -> bool {
+        self.variables.contains_key(name) || self.formulas.iter().any(|(formula, ..)| formula == name)
+    }
 
-    // command line usage
-    if args.len() > 1 {
-        let input = args[1].clone();
-        match calculator.evaluate_expression(&input) {
-            Ok(result) => println!("{}", result),
-            Err(msg) => println!("Error: {}", msg)
+    // fails for names that would be read as anything but that variable, like sin or pi
+    fn check_variable_name(&self, name: &str) -> Result<(), String> {
+        match self.tokenize(name)?.as_slice() {
+            [Token::Variable{ name: ref variable }] if variable == name => Ok(()),
+            _ => Err(format!("'{}' can't be the name of a variable", name)),
         }
     }
 
-    // console usage
-    else {
-        loop {
-            print!(">>> ");
-            io::stdout().flush().unwrap();
+    // formulas that used the variable see it as zero afterwards, like any variable never assigned
+    fn delete_variable(&mut self, name: &str) -> Result<(), String> {
+        if !self.is_defined(name) {
+            return Err(format!("Unknown variable '{}'", name));
+        }
+        self.check_writable(name)?;
+        self.variables.remove(name);
+        self.formulas.retain(|(formula, ..)| formula != name);
+        Ok(())
+    }
+
+    // removes const declarations as well
+    fn clear_variables(&mut self) {
+        self.variables.clear();
+        self.formulas.clear();
+        self.read_only.clear();
+    }
+
+    // formulas that use the variable are changed to use the new name
+    fn rename_variable(&mut self, from: &str, to: &str) -> Result<(), String> {
+        if !self.is_defined(from) {
+            return Err(format!("Unknown variable '{}'", from));
+        }
+        self.check_variable_name(to)?;
+        // a name formulas already use would make them refer to the renamed variable
+        if self.is_defined(to) || !self.dependents(to).is_empty() {
+            return Err(format!("Cannot rename {} to {}, {} is already used", from, to, to));
+        }
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+        if let Some(value) = self.variables.remove(from) {
+            self.variables.insert(String::from(to), value);
+        }
+        let renaming = [(String::from(from), Expr::Variable(String::from(to)))];
+        for (formula, expr) in self.formulas.iter_mut() {
+            if formula == from {
+                *formula = String::from(to);
+            }
+            *expr = symbolic::substitute(expr, &renaming);
+        }
+        for (variable, ..) in self.read_only.iter_mut() {
+            if variable == from {
+                *variable = String::from(to);
+            }
+        }
+        Ok(())
+    }
 
-            if input.len() > 1 {
-                match calculator.evaluate_expression(&input) {
-                    Ok(result) => println!("[{}] = {}", calculator.results.len(), result),
-                    Err(msg) => println!("Error: {}", msg)
+    // the chain of formulas through which an expression uses a variable, if it does
+    fn dependency_path(&self, expr: &Expr, name: &str) -> Option<Vec<String>> {
+        let mut free = Vec::new();
+        expr.free_variables(&mut Vec::new(), &mut free);
+        for variable in free {
+            if variable == name {
+                return Some(vec![variable]);
+            }
+            if let Some((_, formula)) = self.formulas.iter().find(|(formula, ..)| *formula == variable) {
+                if let Some(mut path) = self.dependency_path(formula, name) {
+                    path.insert(0, variable);
+                    return Some(path);
                 }
             }
         }
+        None
     }
-}
 
-#[test]
-fn test_boolean() {
-    assert_eq!(Ok(1.0), Calculator::new().evaluate_expression(&String::from("
-		not false and true or false and true nand false or true and (true or false and (false nor (true and false)))"
-	)));
-}
+    // formulas that use a variable, directly or through other formulas
+    fn dependents(&self, name: &str) -> Vec<String> {
+        self.formulas.iter()
+            .filter(|(_, formula)| self.dependency_path(formula, name).is_some())
+            .map(|(formula, ..)| formula.clone())
+            .collect()
+    }
 
-#[test]
-fn test_relational() {
-    assert_eq!(Ok(1.0), Calculator::new().evaluate_expression(&String::from("
-        8.0 > 7.9999 and 8 >= 8.0 and 8 < 9 and 1 <= 1"
-    )));
-}
+    // formulas only see variables, not the parameters of the lambda they're used in
+    fn evaluate_formula(&mut self, name: &str) -> Option<Result<Value, String>> {
+        let expr = self.formulas.iter().find(|(formula, ..)| formula == name)?.1.clone();
+        let outer = std::mem::take(&mut self.locals);
+        let result = self.evaluate_tree(&expr);
+        self.locals = outer;
+        Some(result)
+    }
 
-#[test]
-fn test_result() {
-    let mut calculator = Calculator::new();
-    calculator.evaluate_expression("1+1").unwrap();
-    calculator.evaluate_expression("1+2").unwrap();
-    calculator.evaluate_expression("1+3").unwrap();
-    calculator.evaluate_expression("1+4").unwrap();
-    assert!(calculator.evaluate_expression(&String::from("result(0)")).is_err());
+    // an expression with its formulas written out and its calls to lambdas replaced by their bodies
+    fn inline(&self, expr: &Expr) -> Result<Expr, String> {
+        let mut expr = expr.clone();
+        // every round replaces one level of formulas, and there are no cycles to go round forever
+        for _ in 0..self.formulas.len() {
+            if self.formulas.iter().all(|(formula, ..)| !symbolic::depends_on(&expr, formula)) {
+                break;
+            }
+            expr = symbolic::substitute(&expr, &self.formulas);
+        }
+        symbolic::inline(&expr, &|name| self.get_variable(name))
+    }
+
+    // evaluates an expression with a variable bound to a value, leaving any variable of the same
+    // name untouched
+    fn evaluate_with(&mut self, name: &str, value: Value, expr: &Expr) -> Result<Value, String> {
+        self.locals.push((String::from(name), value));
+        let result = self.evaluate_tree(expr);
+        self.locals.pop();
+        result
+    }
+
+    // like evaluate_with for several variables at once
+    fn evaluate_with_all(&mut self, names: &[String], values: &[f64], expr: &Expr) -> Result<Value, String> {
+        let depth = self.locals.len();
+        self.locals.extend(names.iter().cloned().zip(values.iter().map(|&value| Value::Number(value))));
+        let result = self.evaluate_tree(expr);
+        self.locals.truncate(depth);
+        result
+    }
+
+    // creates a function which captures the current values of the variables used in its body
+    fn make_lambda(&self, parameters: Vec<String>, body: Expr) -> Value {
+        let mut bound = parameters.clone();
+        let mut free = Vec::new();
+        body.free_variables(&mut bound, &mut free);
+
+        let mut captures = Vec::new();
+        for name in free {
+            if let Some(value) = self.get_variable(&name) {
+                captures.push((name, value));
+            }
+        }
+        Value::Function(Rc::new(Lambda{ parameters, body, captures }))
+    }
+
+    fn call_function(&mut self, function: &Value, arguments: Vec<Value>) -> Result<Value, String> {
+        let lambda = match *function {
+            Value::Function(ref lambda) => lambda.clone(),
+            Value::Polynomial(ref polynomial) => {
+                if arguments.len() != 1 {
+                    return Err(format!("Expected 1 arguments but found {}", arguments.len()));
+                }
+                return Ok(Value::Number(polynomial.eval(arguments[0].to_number()?)));
+            },
+            _ => return Err(format!("Expected a function but found a {}", function.type_name()))
+        };
+
+        if arguments.len() != lambda.parameters.len() {
+            return Err(format!("Expected {} arguments but found {}", lambda.parameters.len(), arguments.len()));
+        }
+
+        // the body only sees the captured values and its parameters
+        let mut scope = lambda.captures.clone();
+        scope.extend(lambda.parameters.iter().cloned().zip(arguments));
+        let outer = std::mem::replace(&mut self.locals, scope);
+        let result = self.evaluate_tree(&lambda.body);
+        self.locals = outer;
+        result
+    }
+
+    fn evaluate_tree(&mut self, expr: &Expr) -> Result<Value, String> {
+        match *expr {
+            Expr::Number(value) => Ok(Value::Number(value)),
+
+            Expr::Integer(value) => match self.word {
+                Some(mode) => Ok(Value::Word(mode.of_unsigned(value)?)),
+                None => Ok(Value::Number(value as f64)),
+            },
+
+            // variables that were never assigned are zero
+            Expr::Variable(ref name) => match self.get_variable(name) {
+                Some(value) => Ok(value),
+                None => self.evaluate_formula(name).unwrap_or(Ok(Value::Number(0.0))),
+            },
+
+            Expr::List(ref items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.evaluate_tree(item)?);
+                }
+                Ok(Value::List(values))
+            },
+
+            Expr::Call(operation, ref args) => self.evaluate_call(operation, args),
+        }
+    }
+
+    fn evaluate_call(&mut self, operation: Operation, args: &[Expr]) -> Result<Value, String> {
+        match operation {
+            Operation::Assignment => {
+                let value = self.evaluate_tree(&args[1])?;
+                if let Expr::Variable(ref name) = args[0] {
+                    self.check_writable(name)?;
+                    self.set_variable(name, value.clone());
+                }
+                return Ok(value);
+            },
+
+            // y := x + 1 keeps the expression, which is evaluated again whenever y is used
+            Operation::Define => {
+                let name = args[0].as_variable().unwrap_or_default().to_string();
+                self.check_writable(&name)?;
+                if let Some(path) = self.dependency_path(&args[1], &name) {
+                    return Err(format!("Circular definition, {} would depend on itself through {} -> {}", name, name, path.join(" -> ")));
+                }
+                let value = self.evaluate_tree(&args[1])?;
+                self.variables.remove(&name);
+                self.formulas.retain(|(formula, ..)| *formula != name);
+                self.formulas.push((name, args[1].clone()));
+                return Ok(value);
+            },
+
+            // declarations are handled by evaluate(), which only accepts them as a whole input
+            Operation::Const => return Err(String::from("const must be the whole input, not part of an expression")),
+
+            // a word size of 0 leaves programmer mode, the arguments are read as plain numbers so that
+            // they needn't fit in the current word
+            Operation::Word => {
+                let mode = self.word.take();
+                let values: Result<Vec<f64>, String> = args.iter().map(|arg| self.evaluate_tree(arg)?.to_number()).collect();
+                self.word = mode;
+                let values = values?;
+                self.set_word(&values)?;
+                return Ok(Value::Number(values[0]));
+            },
+
+            // the setting is named by an identifier, as in format(sci) or format(digits, 10)
+            Operation::Format => {
+                let setting = match args[0].as_variable() {
+                    Some(setting) => setting.to_string(),
+                    None => return Err(String::from("Expected the name of a format setting")),
+                };
+                let value = match args.get(1) {
+                    Some(arg) => Some(self.evaluate_tree(arg)?.to_number()?),
+                    None => None,
+                };
+                self.format.set(&setting, value)?;
+                return Ok(Value::Number(value.unwrap_or(1.0)));
+            },
+
+            // the unit is named by an identifier, as in angle(deg)
+            Operation::AngleMode => {
+                match args[0].as_variable() {
+                    Some(unit) => self.angle = Angle::parse(unit)?,
+                    None => return Err(String::from("Expected an angle unit, rad, deg or grad")),
+                }
+                return Ok(Value::Number(1.0));
+            },
+
+            // negative literals like -128 fit in a signed word even though 128 doesn't
+            Operation::Negation if self.word.is_some() && matches!(args[0], Expr::Integer(value) if value <= 1 << 127) => {
+                if let (Some(mode), Expr::Integer(value)) = (self.word, &args[0]) {
+                    return Ok(Value::Word(mode.of_signed((*value as i128).wrapping_neg())?));
+                }
+            },
+
+            Operation::Lambda => {
+                let parameters = args[0].parameter_names()?;
+                return Ok(self.make_lambda(parameters, args[1].clone()));
+            },
+
+            Operation::Call => {
+                let function = self.evaluate_tree(&args[0])?;
+                if let (Expr::Variable(name), Value::Number(..)) = (&args[0], &function) {
+                    return Err(format!("Unknown function '{}'", name));
+                }
+                let mut arguments = Vec::new();
+                for arg in &args[1..] {
+                    arguments.push(self.evaluate_tree(arg)?);
+                }
+                return self.call_function(&function, arguments);
+            },
+
+            Operation::Sum | Operation::Product => {
+                let name = args[0].as_variable().unwrap_or_default().to_string();
+                let start = self.evaluate_tree(&args[1])?.to_number()?;
+                let end = self.evaluate_tree(&args[2])?.to_number()?;
+                let step = match args.get(4) {
+                    Some(arg) => self.evaluate_tree(arg)?.to_number()?,
+                    None => 1.0
+                };
+                if step == 0.0 || !step.is_finite() || !start.is_finite() || !end.is_finite() {
+                    return Err(String::from("Range bounds and step must be finite and the step non-zero"));
+                }
+
+                // an empty range gives the identity
+                let count = ((end - start) / step).floor() + 1.0;
+                if count > MAX_ITERATIONS as f64 {
+                    return Err(format!("Range exceeds {} iterations", MAX_ITERATIONS));
+                }
+
+                let is_sum = matches!(operation, Operation::Sum);
+                let mut result = if is_sum { 0.0 } else { 1.0 };
+                for i in 0..count.max(0.0) as usize {
+                    let index = Value::Number(start + step * i as f64);
+                    let term = self.evaluate_with(&name, index, &args[3])?.to_number()?;
+                    if is_sum { result += term } else { result *= term }
+                }
+                return Ok(Value::Number(result));
+            },
+
+            Operation::Diff => {
+                let name = args[1].as_variable().unwrap_or_default().to_string();
+                let expr = self.inline(&args[0])?;
+                let derivative = symbolic::differentiate(&expr, &name, self.angle.radians())?;
+                let derivative = symbolic::simplify(&derivative, &|operation, params| self.calculate(operation, params).ok());
+                return match args.get(2) {
+                    Some(point) => {
+                        let point = self.evaluate_tree(point)?;
+                        self.evaluate_with(&name, point, &derivative)
+                    },
+                    None => Ok(self.make_lambda(vec![name], derivative))
+                };
+            },
+
+            Operation::Integrate => {
+                let name = args[1].as_variable().unwrap_or_default().to_string();
+                let a = self.evaluate_tree(&args[2])?.to_number()?;
+                let b = self.evaluate_tree(&args[3])?.to_number()?;
+                let tolerance = match args.get(4) {
+                    Some(arg) => self.evaluate_tree(arg)?.to_number()?,
+                    None => DEFAULT_TOLERANCE
+                };
+                if tolerance <= 0.0 || tolerance.is_nan() {
+                    return Err(String::from("Tolerance must be positive"));
+                }
+
+                let mut integrand = |x: f64| {
+                    let y = self.evaluate_with(&name, Value::Number(x), &args[0])?.to_number()?;
+                    if !y.is_finite() {
+                        return Err(format!("Integrand is not finite at {} = {}", name, x));
+                    }
+                    Ok(y)
+                };
+                let (value, error) = numeric::integrate(&mut integrand, a, b, tolerance)?;
+                return Ok(Value::List(vec![Value::Number(value), Value::Number(error)]));
+            },
+
+            Operation::Equation => return Err(String::from("Assignment to non-variable")),
+
+            Operation::Solve | Operation::Root => {
+                let name = args[1].as_variable().unwrap_or_default().to_string();
+
+                // equations are solved as lhs - rhs = 0
+                let expr = match args[0] {
+                    Expr::Call(Operation::Equation, ref sides) => Expr::Call(Operation::Subtraction, sides.clone()),
+                    ref expr => expr.clone()
+                };
+                let derivative = self.inline(&expr)
+                    .and_then(|expr| symbolic::differentiate(&expr, &name, self.angle.radians()))
+                    .ok();
+
+                let mut bounds = Vec::new();
+                for arg in &args[2..] {
+                    bounds.push(self.evaluate_tree(arg)?.to_number()?);
+                }
+
+                let mut objective = Univariate{ calculator: self, variable: &name, expr: &expr, derivative };
+                return match (operation, bounds.len()) {
+                    (Operation::Solve, 2) => {
+                        let roots = numeric::roots(&mut objective, bounds[0], bounds[1], DEFAULT_TOLERANCE)?;
+                        Ok(Value::List(roots.into_iter().map(Value::Number).collect()))
+                    },
+                    (_, 2) => Ok(Value::Number(numeric::brent(&mut objective, bounds[0], bounds[1], DEFAULT_TOLERANCE)?)),
+                    _ => {
+                        let guess = bounds.first().cloned().unwrap_or(0.0);
+                        Ok(Value::Number(numeric::root(&mut objective, guess, DEFAULT_TOLERANCE)?))
+                    }
+                };
+            },
+
+            // with a variable the first argument is an expression, otherwise the coefficients
+            Operation::Poly if args.len() == 2 => {
+                let name = args[1].as_variable().unwrap_or_default().to_string();
+                let expr = self.inline(&args[0])?;
+                let polynomial = Polynomial::from_expr(&expr, &name, &mut |expr| self.evaluate_tree(expr)?.to_number())?;
+                return Ok(Value::Polynomial(polynomial));
+            },
+
+            // one variable is searched for within [a, b], a list of them from a starting point, either
+            // may be followed by the tolerance and the most iterations
+            Operation::Minimize | Operation::Maximize => {
+                let names = args[1].parameter_names()?;
+                let scalar = args[1].as_variable().is_some();
+                let options = if scalar { 4 } else { 3 };
+                if args.len() < options {
+                    return Err(format!("Expected {} arguments but found {}", options, args.len()));
+                }
+                if args.len() > options + 2 {
+                    return Err(format!("Expected at most {} arguments but found {}", options + 2, args.len()));
+                }
+
+                let tolerance = match args.get(options) {
+                    Some(arg) => self.evaluate_tree(arg)?.to_number()?,
+                    None => DEFAULT_TOLERANCE
+                };
+                if tolerance <= 0.0 || tolerance.is_nan() {
+                    return Err(String::from("Tolerance must be positive"));
+                }
+                let iterations = match args.get(options + 1) {
+                    Some(arg) => self.evaluate_tree(arg)?.to_number()?,
+                    None => MAX_ITERATIONS as f64
+                };
+                if iterations < 1.0 || iterations.fract() != 0.0 {
+                    return Err(String::from("Iterations must be a positive integer"));
+                }
+
+                // maximizing is minimizing the negated expression
+                let sign = if let Operation::Maximize = operation { -1.0 } else { 1.0 };
+                if scalar {
+                    let a = self.evaluate_tree(&args[2])?.to_number()?;
+                    let b = self.evaluate_tree(&args[3])?.to_number()?;
+                    if !a.is_finite() || !b.is_finite() {
+                        return Err(String::from("Bounds must be finite"));
+                    }
+                    let mut objective = |x: f64| Ok(sign * self.evaluate_with_all(&names, &[x], &args[0])?.to_number()?);
+                    let (x, y) = numeric::minimize_scalar(&mut objective, a, b, tolerance, iterations as usize)?;
+                    return Ok(Value::List(vec![Value::Number(x), Value::Number(sign * y)]));
+                }
+
+                let mut start = Vec::new();
+                for value in self.evaluate_tree(&args[2])?.to_list()? {
+                    start.push(value.to_number()?);
+                }
+                if start.len() != names.len() {
+                    return Err(format!("Expected {} starting values but found {}", names.len(), start.len()));
+                }
+                let mut objective = |x: &[f64]| Ok(sign * self.evaluate_with_all(&names, x, &args[0])?.to_number()?);
+                let (x, y) = numeric::nelder_mead(&mut objective, &start, tolerance, iterations as usize)?;
+                let argument = Value::List(x.into_iter().map(Value::Number).collect());
+                return Ok(Value::List(vec![argument, Value::Number(sign * y)]));
+            },
+
+            Operation::Simplify => {
+                let name = args[1].as_variable().unwrap_or_default().to_string();
+                let expr = self.inline(&args[0])?;
+                let simplified = symbolic::simplify(&expr, &|operation, params| self.calculate(operation, params).ok());
+                return Ok(self.make_lambda(vec![name], simplified));
+            },
+
+            _ => { }
+        }
+
+        let mut values = Vec::new();
+        for arg in args {
+            values.push(self.evaluate_tree(arg)?);
+        }
+
+        // in programmer mode operators on integers are carried out exactly in the word size
+        if let Some(mode) = self.word {
+            if word::is_supported(operation) && values.iter().any(|value| matches!(value, Value::Word(..))) {
+                return word_arithmetic(mode, operation, &values).map(Value::Word);
+            }
+        }
+
+        let result = match operation {
+            Operation::Result => {
+                let index = values[0].to_number()? as usize;
+                if index < 1 || index > self.results.len() {
+                    return Err(String::from("Index out of range"));
+                }
+                return Ok(self.results[index - 1].clone());
+            },
+
+            Operation::Length => values[0].to_list()?.len() as f64,
+
+            Operation::Index => {
+                let items = values[0].to_list()?;
+                let index = values[1].to_number()?;
+                if index.fract() != 0.0 || index < 0.0 || index >= items.len() as f64 {
+                    return Err(String::from("Index out of range"));
+                }
+                return Ok(items[index as usize].clone());
+            },
+
+            Operation::Range => {
+                let start = values[0].to_number()?;
+                let end = values[1].to_number()?;
+                let step = if values.len() > 2 { values[2].to_number()? } else { 1.0 };
+                if step == 0.0 || !step.is_finite() {
+                    return Err(String::from("Range step must be a non-zero number"));
+                }
+                let count = ((end - start) / step).floor() + 1.0;
+                if count > MAX_LIST_LENGTH as f64 {
+                    return Err(String::from("Range is too large"));
+                }
+                let items = (0..count.max(0.0) as usize).map(|i| Value::Number(start + step * i as f64));
+                return Ok(Value::List(items.collect()));
+            },
+
+            Operation::Map => {
+                let mut items = Vec::new();
+                for item in values[1].to_list()? {
+                    items.push(self.call_function(&values[0], vec![item.clone()])?);
+                }
+                return Ok(Value::List(items));
+            },
+
+            Operation::Filter => {
+                let mut items = Vec::new();
+                for item in values[1].to_list()? {
+                    if self.call_function(&values[0], vec![item.clone()])?.to_number()? != 0.0 {
+                        items.push(item.clone());
+                    }
+                }
+                return Ok(Value::List(items));
+            },
+
+            Operation::Reduce => {
+                let items = values[1].to_list()?;
+                let (mut accumulator, rest) = match values.get(2) {
+                    Some(initial) => (initial.clone(), items),
+                    None if !items.is_empty() => (items[0].clone(), &items[1..]),
+                    None => return Err(String::from("Cannot reduce an empty list without an initial value"))
+                };
+                for item in rest {
+                    accumulator = self.call_function(&values[0], vec![accumulator, item.clone()])?;
+                }
+                return Ok(accumulator);
+            },
+
+            Operation::Poly => {
+                let mut coefficients = Vec::new();
+                for item in values[0].to_list()? {
+                    coefficients.push(item.to_number()?);
+                }
+                return Ok(Value::Polynomial(Polynomial::from_descending(&coefficients)));
+            },
+
+            Operation::PolyDiv => {
+                let (quotient, remainder) = values[0].to_polynomial()?.div_rem(&values[1].to_polynomial()?)?;
+                return Ok(Value::List(vec![Value::Polynomial(quotient), Value::Polynomial(remainder)]));
+            },
+
+            Operation::PolyGcd => return Ok(Value::Polynomial(values[0].to_polynomial()?.gcd(&values[1].to_polynomial()?))),
+
+            Operation::PolyDer => return Ok(Value::Polynomial(values[0].to_polynomial()?.derivative())),
+
+            // each root is a pair of its real and imaginary parts
+            Operation::Roots => {
+                let roots = values[0].to_polynomial()?.roots()?;
+                let pairs = roots.iter().map(|z| Value::List(vec![Value::Number(z.re), Value::Number(z.im)]));
+                return Ok(Value::List(pairs.collect()));
+            },
+
+            Operation::Coeffs => {
+                let coefficients = values[0].to_polynomial()?.descending();
+                return Ok(Value::List(coefficients.into_iter().map(Value::Number).collect()));
+            },
+
+            Operation::Degree => values[0].to_polynomial()?.degree() as f64,
+
+            Operation::Identity | Operation::Negation | Operation::Addition | Operation::Subtraction |
+            Operation::Multiplication | Operation::Division | Operation::Power
+                if values.iter().any(|value| matches!(value, Value::Polynomial(..))) => {
+                return polynomial_arithmetic(operation, &values).map(Value::Polynomial);
+            },
+
+            // the state is a number or a list for systems, with samples the whole trajectory is a
+            // table with a row [t, y...] for each sample
+            Operation::Ode => {
+                let t0 = values[1].to_number()?;
+                let t1 = values[3].to_number()?;
+                let (y0, system) = match values[2] {
+                    Value::List(ref items) => (items.iter().map(Value::to_number).collect::<Result<Vec<_>, _>>()?, true),
+                    ref value => (vec![value.to_number()?], false),
+                };
+                if !t0.is_finite() || !t1.is_finite() {
+                    return Err(String::from("ODE time bounds must be finite"));
+                }
+
+                let times = match values.get(4) {
+                    Some(samples) => {
+                        let samples = samples.to_number()?;
+                        if samples < 2.0 || samples.fract() != 0.0 || samples > MAX_LIST_LENGTH as f64 {
+                            return Err(String::from("ODE samples must be an integer of at least 2"));
+                        }
+                        let intervals = samples - 1.0;
+                        (0..samples as usize).map(|i| t0 + (t1 - t0) * i as f64 / intervals).collect()
+                    },
+                    None => vec![t0, t1]
+                };
+
+                let function = values[0].clone();
+                let state = |y: &[f64]| match system {
+                    true => Value::List(y.iter().cloned().map(Value::Number).collect()),
+                    false => Value::Number(y[0]),
+                };
+                let mut derivative = |t: f64, y: &[f64]| match self.call_function(&function, vec![Value::Number(t), state(y)])? {
+                    Value::List(ref items) if system => items.iter().map(Value::to_number).collect(),
+                    value => Ok(vec![value.to_number()?]),
+                };
+                let states = numeric::rk45(&mut derivative, t0, &y0, &times[1..], DEFAULT_TOLERANCE)?;
+
+                if values.len() == 4 {
+                    return Ok(state(&states[states.len() - 1]));
+                }
+                let rows = times.iter().zip(std::iter::once(&y0).chain(&states)).map(|(&t, y)| {
+                    Value::List(std::iter::once(t).chain(y.iter().cloned()).map(Value::Number).collect())
+                });
+                return Ok(Value::List(rows.collect()));
+            },
+
+            Operation::Npv => finance::npv(values[0].to_number()?, &values[1].to_numbers()?),
+
+            Operation::Irr => {
+                let guess = match values.get(1) { Some(value) => value.to_number()?, None => 0.1 };
+                finance::irr(&values[0].to_numbers()?, guess)?
+            },
+
+            Operation::Xirr => {
+                let guess = match values.get(2) { Some(value) => value.to_number()?, None => 0.1 };
+                finance::xirr(&values[0].to_numbers()?, &values[1].to_numbers()?, guess)?
+            },
+
+            Operation::Amortize => {
+                let periods = values[2].to_number()?;
+                if periods > MAX_LIST_LENGTH as f64 {
+                    return Err(String::from("Amortization table is too large"));
+                }
+                let rows = finance::amortize(values[0].to_number()?, values[1].to_number()?, periods)?;
+                let rows = rows.iter().map(|row| Value::List(row.iter().cloned().map(Value::Number).collect()));
+                return Ok(Value::List(rows.collect()));
+            },
+
+            // number theory is exact on 64-bit integers, which only words and integer literals hold
+            // beyond 2^53
+            Operation::IsPrime | Operation::NextPrime | Operation::ModPow | Operation::ModInv | Operation::Totient |
+            Operation::Factor | Operation::Divisors | Operation::Egcd => {
+                let params = values.iter().map(Value::to_number).collect::<Result<Vec<_>, _>>()?;
+                let n: Option<Vec<i128>> = args.iter().zip(&values)
+                    .map(|(arg, value)| exact_integer(arg, value).filter(|n| n.unsigned_abs() <= u64::MAX as u128))
+                    .collect();
+                let results = match n {
+                    Some(n) => number_theory(operation, &n)?,
+                    None => None,
+                };
+                let results = results.ok_or_else(|| undefined(operation, &params))?;
+
+                // results are words in programmer mode, otherwise numbers as long as they're exact
+                let mut output = Vec::new();
+                for n in results {
+                    output.push(match self.word {
+                        Some(mode) => Value::Word(mode.of_signed(n)?),
+                        None => Value::Number(integer::exact_f64(n).ok_or_else(|| undefined(operation, &params))?),
+                    });
+                }
+                return Ok(match operation {
+                    Operation::Factor | Operation::Divisors | Operation::Egcd => Value::List(output),
+                    _ => output.remove(0),
+                });
+            },
+
+            Operation::Rand => self.random.next_f64(),
+
+            Operation::RandInt => {
+                let (a, b) = (values[0].to_number()?, values[1].to_number()?);
+                // integers beyond 2^53 can't all be represented, so they can't be drawn evenly
+                let exact = |x: f64| x.fract() == 0.0 && x.abs() <= 9007199254740992.0;
+                if !exact(a) || !exact(b) || !exact(b - a) || a > b {
+                    return Err(undefined(operation, &[a, b]));
+                }
+                a + self.random.below((b - a) as u64 + 1) as f64
+            },
+
+            Operation::RandN => {
+                let mean = match values.first() { Some(value) => value.to_number()?, None => 0.0 };
+                let deviation = match values.get(1) { Some(value) => value.to_number()?, None => 1.0 };
+                if deviation < 0.0 || deviation.is_nan() {
+                    return Err(undefined(operation, &[mean, deviation]));
+                }
+                mean + deviation * self.random.normal()
+            },
+
+            Operation::Choice => {
+                let items = values[0].to_list()?;
+                if items.is_empty() {
+                    return Err(String::from("Cannot choose from an empty list"));
+                }
+                return Ok(items[self.random.below(items.len() as u64) as usize].clone());
+            },
+
+            Operation::Seed => {
+                let seed = values[0].to_number()?;
+                if seed.fract() != 0.0 || !seed.is_finite() {
+                    return Err(undefined(operation, &[seed]));
+                }
+                self.seed(seed as i64 as u64);
+                seed
+            },
+
+            Operation::SumOver => {
+                let mut sum = 0.0;
+                for item in values[1].to_list()? {
+                    sum += self.call_function(&values[0], vec![item.clone()])?.to_number()?;
+                }
+                sum
+            },
+
+            _ => {
+                let mut params = Vec::new();
+                for value in &values {
+                    params.push(value.to_number()?);
+                }
+                self.calculate(operation, &params)?
+            }
+        };
+
+        Ok(Value::Number(result))
+    }
+
+    fn calculate(&self, operation: Operation, params: &[f64]) -> Result<f64, String> {
+        let optional = |i: usize, default: f64| params.get(i).cloned().unwrap_or(default);
+        let result = match operation {
+            Operation::Identity => params[0],
+            Operation::Negation => -params[0],
+            Operation::Not => if params[0] == 0.0 { 1.0 } else { 0.0 },
+            Operation::Addition => params[0] + params[1],
+            Operation::Subtraction => params[0] - params[1],
+            Operation::Multiplication => params[0] * params[1],
+            Operation::Division => params[0] / params[1],
+            Operation::Modulus => params[0] % params[1],
+            Operation::Equality => if params[0] == params[1] { 1.0 } else { 0.0 },
+            Operation::Inequality => if params[0] != params[1] { 1.0 } else { 0.0 },
+            Operation::Less => if params[0] < params[1] { 1.0 } else { 0.0 },
+            Operation::LessEqual => if params[0] <= params[1] { 1.0 } else { 0.0 },
+            Operation::Greater => if params[0] > params[1] { 1.0 } else { 0.0 },
+            Operation::GreaterEqual => if params[0] >= params[1] { 1.0 } else { 0.0 },
+            Operation::And => if params[0] != 0.0 && params[1] != 0.0 { 1.0 } else { 0.0 },
+            Operation::Nand => if params[0] != 0.0 && params[1] != 0.0 { 0.0 } else { 1.0 },
+            Operation::Or => if params[0] != 0.0 || params[1] != 0.0 { 1.0 } else { 0.0 },
+            Operation::Nor => if params[0] != 0.0 || params[1] != 0.0 { 0.0 } else { 1.0 },
+            Operation::Power => params[0].powf(params[1]),
+            Operation::Sin => self.angle.sin(params[0]),
+            Operation::Cos => self.angle.cos(params[0]),
+            Operation::Tan => self.angle.tan(params[0]),
+            Operation::DegreeSign => self.angle.degrees(params[0]),
+            Operation::Abs => params[0].abs(),
+            Operation::Sqrt => params[0].sqrt(),
+            Operation::Radians => params[0].to_radians(),
+            Operation::Degrees => params[0].to_degrees(),
+            Operation::Min => f64::min(params[0], params[1]),
+            Operation::Max => f64::max(params[0], params[1]),
+            Operation::Ln => params[0].ln(),
+            Operation::Log2 => params[0].log2(),
+            Operation::Log10 => params[0].log10(),
+            Operation::Exp => params[0].exp(),
+            Operation::Sign => params[0].signum(),
+            Operation::Asin => self.angle.inverse(params[0].asin()),
+            Operation::Acos => self.angle.inverse(params[0].acos()),
+            Operation::Atan => self.angle.inverse(params[0].atan()),
+            Operation::Atan2 => self.angle.inverse(params[0].atan2(params[1])),
+            Operation::Sinh => params[0].sinh(),
+            Operation::Cosh => params[0].cosh(),
+            Operation::Tanh => params[0].tanh(),
+            Operation::Asinh => params[0].asinh(),
+            Operation::Acosh => params[0].acosh(),
+            Operation::Atanh => params[0].atanh(),
+            Operation::Floor => params[0].floor(),
+            Operation::Ceil => params[0].ceil(),
+            Operation::Trunc => params[0].trunc(),
+            Operation::Frac => params[0].fract(),
+            Operation::Hypot => params[0].hypot(params[1]),
+            Operation::Cbrt => params[0].cbrt(),
+            Operation::Lerp => params[0] + (params[1] - params[0]) * params[2],
+            Operation::Round => {
+                let digits = optional(1, 0.0);
+                if digits.fract() != 0.0 {
+                    return Err(undefined(operation, params));
+                }
+                // dividing by a power of ten that's exact keeps the rounding exact. past 10^308 the
+                // scale would overflow, and every number already rounds to 0 at 10^308
+                let digits = digits.clamp(-308.0, 308.0);
+                let scale = 10f64.powi(digits.abs() as i32);
+                match digits >= 0.0 {
+                    true if (params[0] * scale).is_finite() => (params[0] * scale).round() / scale,
+                    true => params[0],
+                    false => (params[0] / scale).round() * scale,
+                }
+            },
+            Operation::NthRoot => {
+                let (x, n) = (params[0], params[1]);
+                if n == 0.0 || n.fract() != 0.0 {
+                    return Err(undefined(operation, params));
+                }
+                // odd roots of negative numbers are real
+                if x < 0.0 && n % 2.0 != 0.0 { -(-x).powf(1.0 / n) } else { x.powf(1.0 / n) }
+            },
+            Operation::Log => {
+                let base = optional(1, 10.0);
+                if base <= 0.0 || base == 1.0 {
+                    return Err(undefined(operation, params));
+                }
+                params[0].ln() / base.ln()
+            },
+            Operation::Clamp => {
+                if params[1] > params[2] || params[1].is_nan() || params[2].is_nan() {
+                    return Err(undefined(operation, params));
+                }
+                params[0].clamp(params[1], params[2])
+            },
+            Operation::Gcd | Operation::Lcm | Operation::Ncr | Operation::Npr => {
+                if params.iter().any(|p| p.fract() != 0.0 || !p.is_finite()) {
+                    return Err(undefined(operation, params));
+                }
+                match operation {
+                    Operation::Gcd => gcd(params[0], params[1]),
+                    Operation::Lcm if params[0] == 0.0 || params[1] == 0.0 => 0.0,
+                    Operation::Lcm => (params[0] / gcd(params[0], params[1]) * params[1]).abs(),
+                    _ => {
+                        let (n, r) = (params[0], params[1]);
+                        if n < 0.0 || r < 0.0 {
+                            return Err(undefined(operation, params));
+                        }
+                        if let Operation::Ncr = operation { choose(n, r) } else { permutations(n, r) }
+                    }
+                }
+            },
+            // outside of programmer mode bitwise operators work on 64-bit signed integers
+            Operation::BitAnd | Operation::BitOr | Operation::BitXor | Operation::BitNot |
+            Operation::ShiftLeft | Operation::ShiftRight | Operation::RotateLeft | Operation::RotateRight => {
+                let mode = WordMode{ bits: 64, signed: true, overflow: Overflow::Wrap };
+                let words = params.iter().map(|&p| mode.of_f64(p)).collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| undefined(operation, params))?;
+                mode.apply(operation, &words)?.to_f64()
+            },
+            Operation::Factorial => special::gamma(params[0] + 1.0),
+            Operation::Gamma => special::gamma(params[0]),
+            Operation::LogGamma => special::lgamma(params[0]),
+            Operation::Beta => special::beta(params[0], params[1]),
+            Operation::Digamma => special::digamma(params[0]),
+            Operation::NormPdf => distribution::normal_pdf(params[0], optional(1, 0.0), optional(2, 1.0)),
+            Operation::NormCdf => distribution::normal_cdf(params[0], optional(1, 0.0), optional(2, 1.0)),
+            Operation::NormInv => distribution::normal_inv(params[0], optional(1, 0.0), optional(2, 1.0)),
+            Operation::TPdf => distribution::t_pdf(params[0], params[1]),
+            Operation::TCdf => distribution::t_cdf(params[0], params[1]),
+            Operation::TInv => distribution::t_inv(params[0], params[1]),
+            Operation::Chi2Pdf => distribution::chi2_pdf(params[0], params[1]),
+            Operation::Chi2Cdf => distribution::chi2_cdf(params[0], params[1]),
+            Operation::Chi2Inv => distribution::chi2_inv(params[0], params[1]),
+            Operation::FPdf => distribution::f_pdf(params[0], params[1], params[2]),
+            Operation::FCdf => distribution::f_cdf(params[0], params[1], params[2]),
+            Operation::FInv => distribution::f_inv(params[0], params[1], params[2]),
+            Operation::BinomPdf => distribution::binomial_pdf(params[0], params[1], params[2]),
+            Operation::BinomCdf => distribution::binomial_cdf(params[0], params[1], params[2]),
+            Operation::BinomInv => distribution::binomial_inv(params[0], params[1], params[2]),
+            Operation::PoissPdf => distribution::poisson_pdf(params[0], params[1]),
+            Operation::PoissCdf => distribution::poisson_cdf(params[0], params[1]),
+            Operation::PoissInv => distribution::poisson_inv(params[0], params[1]),
+            Operation::ExpPdf => distribution::exponential_pdf(params[0], optional(1, 1.0)),
+            Operation::ExpCdf => distribution::exponential_cdf(params[0], optional(1, 1.0)),
+            Operation::ExpInv => distribution::exponential_inv(params[0], optional(1, 1.0)),
+            Operation::UnifPdf => distribution::uniform_pdf(params[0], optional(1, 0.0), optional(2, 1.0)),
+            Operation::UnifCdf => distribution::uniform_cdf(params[0], optional(1, 0.0), optional(2, 1.0)),
+            Operation::UnifInv => distribution::uniform_inv(params[0], optional(1, 0.0), optional(2, 1.0)),
+            Operation::Erf => special::erf(params[0]),
+            Operation::Pv => finance::pv(params[0], params[1], params[2], optional(3, 0.0), optional(4, 0.0))?,
+            Operation::Fv => finance::fv(params[0], params[1], params[2], optional(3, 0.0), optional(4, 0.0))?,
+            Operation::Pmt => finance::pmt(params[0], params[1], params[2], optional(3, 0.0), optional(4, 0.0))?,
+            Operation::Nper => finance::nper(params[0], params[1], params[2], optional(3, 0.0), optional(4, 0.0))?,
+            Operation::Rate => finance::rate(params[0], params[1], params[2], optional(3, 0.0), optional(4, 0.0), optional(5, 0.1))?,
+            Operation::Erfc => special::erfc(params[0]),
+            _ => return Err(String::from("Invalid operation"))
+        };
+
+        // functions of defined arguments never quietly give NaN, and neither do powers like
+        // (-1)^0.5 or remainders like x % 0. infinities at poles are fine
+        let function = operation.operator_precedence().is_none()
+            || matches!(operation, Operation::Factorial | Operation::Power | Operation::Modulus);
+        if result.is_nan() && function && !params.iter().any(|p| p.is_nan()) {
+            return Err(undefined(operation, params));
+        }
+        Ok(result)
+    }
+
+    fn read(&self, input: &str) -> Result<Expr, String> {
+        let tokens = self.tokenize(input)?;
+        let rpn_tokens = self.parse(tokens)?;
+        self.build(rpn_tokens)
+    }
+
+    fn evaluate(&mut self, input: &str) -> Result<Value, String> {
+        let tree = self.read(input)?;
+        if let Expr::Call(Operation::Const, ref args) = tree {
+            return self.declare_const(&args[0]);
+        }
+        let result = self.evaluate_tree(&tree)?;
+        self.results.push(result.clone());
+        Ok(result)
+    }
+
+    // const g = 9.81 assigns once and then refuses to assign again. the declaration is only
+    // recorded once its result is, so a failed one leaves nothing behind
+    fn declare_const(&mut self, assignment: &Expr) -> Result<Value, String> {
+        let name = match *assignment {
+            Expr::Call(Operation::Assignment, ref sides) => sides[0].as_variable().map(String::from),
+            _ => None,
+        };
+        let name = name.ok_or_else(|| String::from("Expected an assignment to a variable after const"))?;
+        let result = self.evaluate_tree(assignment)?;
+        self.results.push(result.clone());
+        self.read_only.push((name, self.results.len()));
+        Ok(result)
+    }
+
+    // parses the input and prints it back in a normalized form, without evaluating it
+    fn normalize(&self, input: &str) -> Result<String, String> {
+        Ok(self.read(input)?.to_string())
+    }
+
+    // writes the variables, formulas and results to a file that load() restores them from
+    fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, session::save(self)).map_err(|err| format!("Cannot save to {}: {}", path, err))
+    }
+
+    fn load(&mut self, path: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("Cannot load {}: {}", path, err))?;
+        session::load(self, &text)
+    }
+
+    fn evaluate_expression(&mut self, input: &str) -> Result<f64, String> {
+        self.evaluate(input)?.to_number()
+    }
+
+    fn new() -> Calculator {
+        let results = Vec::new();
+        let variables = HashMap::new();
+        let locals = Vec::new();
+        let random = Random::from_time();
+        Calculator{ results, variables, locals, random, word: None, format: Format::default(), angle: Angle::Radians, read_only: Vec::new(), formulas: Vec::new() }
+    }
+
+    // programmer mode by the arguments of word(bits, signed, checked), a size of 0 leaves it
+    fn set_word(&mut self, values: &[f64]) -> Result<(), String> {
+        let (bits, signed, checked) = (values[0], values.get(1).is_none_or(|&x| x != 0.0), values.get(2).is_some_and(|&x| x != 0.0));
+        self.word = match bits {
+            0.0 => None,
+            _ if bits.fract() != 0.0 => return Err(undefined(Operation::Word, values)),
+            _ => Some(WordMode::new(bits as u32, signed, if checked { Overflow::Check } else { Overflow::Wrap })?),
+        };
+        Ok(())
+    }
+
+    // a setting by name with its values written out, as in :set angle deg, :set word 32 1 1 or
+    // :set digits 10 for any setting of format()
+    fn set(&mut self, setting: &str, values: &[&str]) -> Result<(), String> {
+        let numbers = || values.iter()
+            .map(|value| value.parse().map_err(|_| format!("Expected a number but found '{}'", value)))
+            .collect::<Result<Vec<f64>, String>>();
+        match (setting, values) {
+            ("angle", [unit]) => self.angle = Angle::parse(unit)?,
+            ("angle", _) => return Err(String::from("Expected an angle unit, rad, deg or grad")),
+            ("word", [_, ..]) if values.len() <= 3 => self.set_word(&numbers()?)?,
+            ("word", _) => return Err(String::from("Expected a word size, then optionally whether it's signed and checked")),
+            (_, []) => self.format.set(setting, None)?,
+            (_, [_]) => self.format.set(setting, Some(numbers()?[0]))?,
+            _ => return Err(format!("Too many values for {}", setting)),
+        }
+        Ok(())
+    }
+
+    // restarts the random numbers from a seed so that they repeat
+    fn seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+}
+
+// one line per variable with its value and type, formulas show their expression as well
+fn print_variables(calculator: &mut Calculator) {
+    for (name, value) in calculator.list_variables() {
+        let formula = calculator.formulas.iter().find(|(formula, ..)| *formula == name).map(|(_, expr)| expr.to_string());
+        let declared = match formula {
+            Some(expr) => format!("{} := {}", name, expr),
+            None => name.clone(),
+        };
+        let kind = if calculator.read_only.iter().any(|(variable, ..)| *variable == name) { "const " } else { "" };
+        match value {
+            Ok(value) => println!("{} = {}  ({}{})", declared, calculator.format.value(&value), kind, value.type_name()),
+            Err(msg) => println!("{}  (Error: {})", declared, msg),
+        }
+    }
+}
+
+const HELP: &str = "\
+Enter an expression to evaluate it, or one of these commands:
+  :help                  this list
+  :quit                  leave, as does Ctrl-D
+  :vars                  list the variables and formulas
+  :delete x              delete a variable
+  :rename x y            rename a variable
+  :clear                 delete every variable
+  :dependents x          list the formulas that use a variable
+  :constants             list the built-in constants, the elementary charge is q_e
+  :history               list the expressions entered so far
+  :set setting [values]  change a setting, like :set digits 10, :set sci, :set angle deg or :set word 32
+  :save file             save the session to a file
+  :load file             restore a session from a file
+  :reset                 start over with nothing defined and the default settings";
+
+// what the console does after a command
+enum Flow {
+    Continue,
+    Quit,
+}
+
+// runs a console command, which starts with a colon so that it can't be taken for an expression
+fn run_command(calculator: &mut Calculator, history: &[String], line: &str) -> Result<Flow, String> {
+    let line = line.trim().trim_start_matches(':');
+    let (command, rest) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+    let rest = rest.trim();
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let name = |arg: &str| arg.to_lowercase();
+    match (command, args.as_slice()) {
+        ("quit", []) | ("q", []) | ("exit", []) => return Ok(Flow::Quit),
+        ("help", []) => println!("{}", HELP),
+        ("vars", []) => print_variables(calculator),
+        ("delete", [variable]) => calculator.delete_variable(&name(variable))?,
+        ("rename", [from, to]) => calculator.rename_variable(&name(from), &name(to))?,
+        ("clear", []) => calculator.clear_variables(),
+        ("dependents", [variable]) => {
+            let dependents = calculator.dependents(&name(variable));
+            if dependents.is_empty() {
+                println!("Nothing depends on {}", variable);
+            }
+            else {
+                println!("{}", dependents.join(", "));
+            }
+        },
+        ("constants", []) => println!("{}", constants::table()),
+        ("history", []) => {
+            for (i, input) in history.iter().enumerate() {
+                println!("{:>4}  {}", i + 1, input);
+            }
+        },
+        ("set", [setting, ref values @ ..]) => calculator.set(setting, values)?,
+        // paths may have spaces in them
+        ("save", [_, ..]) => calculator.save(rest)?,
+        ("load", [_, ..]) => calculator.load(rest)?,
+        ("reset", []) => *calculator = Calculator::new(),
+        _ => return Err(format!("Unknown command ':{}', :help lists the commands and their arguments", line)),
+    }
+    Ok(Flow::Continue)
+}
+
+// reads inputs until the end of them or :quit, printing each result
+fn console(calculator: &mut Calculator, reader: &mut dyn BufRead, autosave: Option<&str>) {
+    let mut history = Vec::new();
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { ">>> " } else { "... " });
+        io::stdout().flush().unwrap();
+
+        match reader.read_line(&mut input) {
+            // the end of the input, as when Ctrl-D is pressed
+            Ok(0) => {
+                println!();
+                break;
+            },
+            Ok(_) => { },
+            Err(err) => {
+                println!("Error: {}", err);
+                break;
+            },
+        }
+
+        // keep reading lines until the expression is complete
+        if is_incomplete(&input) {
+            continue;
+        }
+
+        if input.trim_start().starts_with(':') {
+            match run_command(calculator, &history, &input) {
+                Ok(Flow::Quit) => break,
+                Ok(Flow::Continue) => { },
+                Err(msg) => println!("Error: {}", msg),
+            }
+        }
+        else if !strip_comments(&input).trim().is_empty() {
+            history.push(input.trim().to_string());
+            // echo input spanning several lines back as a single normalized line
+            if input.trim_end().contains('\n') {
+                if let Ok(normalized) = calculator.normalize(&input) {
+                    println!("    {}", normalized);
+                }
+            }
+            match calculator.evaluate(&input) {
+                // integers in programmer mode are shown in every base at once
+                Ok(Value::Word(word)) => println!("[{}] = {}  {}  {}", calculator.results.len(), word, word.hex(), word.binary()),
+                Ok(result) => println!("[{}] = {}", calculator.results.len(), calculator.format.value(&result)),
+                Err(msg) => println!("Error: {}", msg)
+            }
+        }
+        if let Some(path) = autosave {
+            if let Err(msg) = calculator.save(path) {
+                println!("Error: {}", msg);
+            }
+        }
+        input.clear();
+    }
+}
+
+fn main() {
+    let args: Vec<_> = std::env::args().collect();
+
+    let mut calculator = Calculator::new();
+
+    // command line usage
+    if args.len() > 1 {
+        let input = args[1].clone();
+        match calculator.evaluate(&input) {
+            Ok(result) => println!("{}", calculator.format.value(&result)),
+            Err(msg) => println!("Error: {}", msg)
+        }
+    }
+
+    // console usage
+    else {
+        // a session named by CALC_SESSION is restored on start and kept saved after every input, so
+        // that it survives however the console is closed
+        let autosave = std::env::var("CALC_SESSION").ok().filter(|path| !path.is_empty());
+        if let Some(ref path) = autosave {
+            if std::path::Path::new(path).exists() {
+                if let Err(msg) = calculator.load(path) {
+                    println!("Error: {}", msg);
+                }
+            }
+        }
+
+        console(&mut calculator, &mut io::stdin().lock(), autosave.as_deref());
+    }
+}
+
+#[test]
+fn test_boolean() {
+    assert_eq!(Ok(1.0), Calculator::new().evaluate_expression(&String::from("
+		not false and true or false and true nand false or true and (true or false and (false nor (true and false)))"
+	)));
+}
+
+#[test]
+fn test_relational() {
+    assert_eq!(Ok(1.0), Calculator::new().evaluate_expression(&String::from("
+        8.0 > 7.9999 and 8 >= 8.0 and 8 < 9 and 1 <= 1"
+    )));
+}
+
+#[test]
+fn test_result() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate_expression("1+1").unwrap();
+    calculator.evaluate_expression("1+2").unwrap();
+    calculator.evaluate_expression("1+3").unwrap();
+    calculator.evaluate_expression("1+4").unwrap();
+    assert!(calculator.evaluate_expression(&String::from("result(0)")).is_err());
     assert_eq!(Ok(2.0), calculator.evaluate_expression(&String::from("result(1)")));
     assert_eq!(Ok(3.0), calculator.evaluate_expression(&String::from("result(2)")));
     assert_eq!(Ok(4.0), calculator.evaluate_expression(&String::from("result(3)")));
@@ -605,3 +2639,981 @@ fn test_mixed() {
         sign(max(12.44343234, 11.84934)))*(1+2+3+4)+3!!-720+2**-2-0.25"
     )));
 }
+
+#[test]
+fn test_comments() {
+    let mut calculator = Calculator::new();
+    assert_eq!(Ok(9.0), calculator.evaluate_expression("
+        # leading comment
+        (1 + 2) // trailing comment
+        * 3     # another one"
+    ));
+    assert_eq!(Ok(0.5), calculator.evaluate_expression("1 / 2 // comments aren't division"));
+    assert!(calculator.evaluate_expression("# nothing but a comment").is_err());
+}
+
+#[test]
+fn test_incomplete() {
+    assert!(is_incomplete("max(1,"));
+    assert!(is_incomplete("(1 + 2"));
+    assert!(is_incomplete("1 +"));
+    assert!(is_incomplete("x = # comment"));
+    assert!(is_incomplete("true and"));
+    assert!(!is_incomplete("(1 + 2) # )"));
+    assert!(!is_incomplete("3!"));
+    assert!(!is_incomplete("brand"));
+    assert!(!is_incomplete("x = 1"));
+    assert!(!is_incomplete(":save out-"));
+    assert!(!is_incomplete("  :load f(1"));
+    assert!(!is_incomplete("# only a comment ("));
+}
+
+#[test]
+fn test_lists() {
+    let mut calculator = Calculator::new();
+    assert_eq!(Ok(Value::List(vec![Value::Number(1.0), Value::Number(2.0), Value::Number(3.0)])),
+        calculator.evaluate("[1, 2, 1 + 2]"));
+    assert_eq!(Ok(String::from("[1, 3, 5]")), calculator.evaluate("range(1, 6, 2)").map(|v| v.to_string()));
+    assert_eq!(Ok(String::from("[]")), calculator.evaluate("range(1, 0)").map(|v| v.to_string()));
+    assert_eq!(Ok(3.0), calculator.evaluate_expression("len([[1, 2], -1, []])"));
+    assert!(calculator.evaluate_expression("[1, 2] + 1").is_err());
+    assert!(calculator.evaluate_expression("[1, 2").is_err());
+}
+
+#[test]
+fn test_lambdas() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate("square = x -> x^2").unwrap();
+    calculator.evaluate("add = (a, b) -> a + b").unwrap();
+    assert_eq!(Ok(9.0), calculator.evaluate_expression("square(3)"));
+    assert_eq!(Ok(7.0), calculator.evaluate_expression("add(3, square(2))"));
+    assert_eq!(Ok(String::from("[1, 4, 9]")), calculator.evaluate("map(square, [1, 2, 3])").map(|v| v.to_string()));
+    assert_eq!(Ok(String::from("[2, 4]")), calculator.evaluate("filter(x -> x % 2 == 0, range(1, 5))").map(|v| v.to_string()));
+    assert_eq!(Ok(10.0), calculator.evaluate_expression("reduce(add, [1, 2, 3, 4])"));
+    assert_eq!(Ok(20.0), calculator.evaluate_expression("reduce((a, x) -> a + x, [1, 2, 3, 4], 10)"));
+    assert_eq!(Ok(14.0), calculator.evaluate_expression("sum_over(x -> x^2, [1, 2, 3])"));
+    assert!(calculator.evaluate_expression("add(1)").is_err());
+    assert!(calculator.evaluate_expression("undefined(1)").is_err());
+
+    // variables are captured by value when the lambda is created
+    calculator.evaluate_expression("n = 2").unwrap();
+    calculator.evaluate("scale = x -> x * n").unwrap();
+    calculator.evaluate_expression("n = 10").unwrap();
+    assert_eq!(Ok(6.0), calculator.evaluate_expression("scale(3)"));
+
+    // parameters don't leak into or clobber variables
+    calculator.evaluate_expression("x = 5").unwrap();
+    assert_eq!(Ok(4.0), calculator.evaluate_expression("square(2)"));
+    assert_eq!(Ok(5.0), calculator.evaluate_expression("x"));
+}
+
+#[test]
+fn test_sum_product() {
+    let mut calculator = Calculator::new();
+    assert_eq!(Ok(5050.0), calculator.evaluate_expression("sum(k, 1, 100, k)"));
+    assert_eq!(Ok(120.0), calculator.evaluate_expression("prod(k, 1, 5, k)"));
+    assert_eq!(Ok(25.0), calculator.evaluate_expression("sum(k, 1, 10, k, 2)"));
+    assert_eq!(Ok(15.0), calculator.evaluate_expression("sum(k, 5, 1, k, -1)"));
+    assert_eq!(Ok(36.0), calculator.evaluate_expression("sum(i, 1, 3, sum(j, 1, 3, i * j))"));
+    let basel = calculator.evaluate_expression("sum(k, 1, 100000, 1/k^2)").unwrap();
+    assert!((basel - std::f64::consts::PI.powi(2) / 6.0).abs() < 1e-4);
+
+    // empty ranges give the identity
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("sum(k, 1, 0, k)"));
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("prod(k, 1, 0, k)"));
+
+    // the index variable doesn't clobber an existing variable
+    calculator.evaluate_expression("k = 42").unwrap();
+    calculator.evaluate_expression("sum(k, 1, 10, k)").unwrap();
+    assert_eq!(Ok(42.0), calculator.evaluate_expression("k"));
+
+    assert!(calculator.evaluate_expression("sum(k, 1, 10, k, 0)").is_err());
+    assert!(calculator.evaluate_expression("sum(k, 1, 10^9, k)").is_err());
+    assert!(calculator.evaluate_expression("sum(2, 1, 10, k)").is_err());
+}
+
+#[test]
+fn test_diff() {
+    let mut calculator = Calculator::new();
+    let mut derivative = |input: &str| calculator.evaluate(input).map(|v| v.to_string());
+    assert_eq!(Ok(String::from("x -> 2 * x")), derivative("diff(x^2, x)"));
+    assert_eq!(Ok(String::from("x -> cos(x)")), derivative("diff(sin(x) + 1, x)"));
+    assert_eq!(Ok(String::from("x -> 3 * a")), derivative("diff(3 * a * x, x)"));
+    assert_eq!(Ok(String::from("t -> -2 * sin(2 * t)")), derivative("diff(cos(2 * t), t)"));
+    assert_eq!(Ok(String::from("x -> x ^ 2 * cos(x) + 2 * x * sin(x)")), derivative("diff(x^2 * sin(x), x)"));
+    assert_eq!(Ok(String::from("x -> (1 - x ^ 2) / (x ^ 2 + 1) ^ 2")), derivative("diff(x / (1 + x^2), x)"));
+    assert_eq!(Ok(String::from("x -> 1 / x")), derivative("diff(ln(x), x)"));
+
+    let mut at = |input: &str| calculator.evaluate_expression(input).unwrap();
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+    assert!(close(at("diff(x^2 * sin(x), x, 2.5)"), 2.0 * 2.5 * 2.5f64.sin() + 2.5 * 2.5 * 2.5f64.cos()));
+    assert!(close(at("diff(x / (1 + x^2), x, 2)"), (1.0 - 4.0) / 25.0));
+    assert!(close(at("diff(2^x, x, 3)"), 8.0 * 2f64.ln()));
+    assert!(close(at("diff(x^x, x, 2)"), 4.0 * (2f64.ln() + 1.0)));
+    assert!(close(at("diff(tan(x), x, 1)"), 1.0 / 1f64.cos().powi(2)));
+    assert!(close(at("diff(sqrt(x) + exp(x), x, 4)"), 0.25 + 4f64.exp()));
+    assert!(close(at("diff(log2(x) + log10(x), x, 3)"), 1.0 / (3.0 * 2f64.ln()) + 1.0 / (3.0 * 10f64.ln())));
+    assert!(close(at("diff(abs(x), x, -3)"), -1.0));
+    assert!(close(at("diff(diff(x^3, x), x, 2)"), 12.0));
+
+    assert!(calculator.evaluate("diff(gcd(x, 2), x)").is_err());
+
+    // calls to lambdas are differentiated through
+    calculator.evaluate("f = t -> t^3 + k").unwrap();
+    calculator.evaluate("k = 5").unwrap();
+    calculator.evaluate("g = diff(f(x), x)").unwrap();
+    assert_eq!(Ok(24.0), calculator.evaluate_expression("diff(f(2 * x), x, 1)"));
+    assert_eq!(Ok(27.0), calculator.evaluate_expression("g(3)"));
+}
+
+#[test]
+fn test_simplify() {
+    let mut calculator = Calculator::new();
+    let mut simplified = |input: &str| calculator.evaluate(input).map(|v| v.to_string());
+    assert_eq!(Ok(String::from("x -> 4 * x")), simplified("simplify(2*x + 3*x - x*1 + 0, x)"));
+    assert_eq!(Ok(String::from("x -> 0")), simplified("simplify(x - x, x)"));
+    assert_eq!(Ok(String::from("x -> 1 / x")), simplified("simplify(x * x^2 / x^4, x)"));
+    assert_eq!(Ok(String::from("x -> x ^ 6")), simplified("simplify((x^2)^3, x)"));
+    assert_eq!(Ok(String::from("x -> 2 * (x + 1) ^ 2")), simplified("simplify((x + 1) * 2 * (1 + x), x)"));
+    assert_eq!(Ok(String::from("x -> 2 * x * y - x ^ 2 + 2")), simplified("simplify(-(x^2) + 3 - 1 + y*x + x*y, x)"));
+    assert_eq!(Ok(String::from("x -> 2 * ln(2) + 6")), simplified("simplify(sqrt(16) + ln(2) * 2 + 2^1, x)"));
+    assert_eq!(Ok(String::from("x -> x ^ (a + 1)")), simplified("simplify(x^a * x, x)"));
+}
+
+#[test]
+fn test_normalize() {
+    let calculator = Calculator::new();
+    let normalized = |input: &str| calculator.normalize(input).unwrap();
+    assert_eq!("1 + 2 * 3", normalized("(1 + (2 * 3))"));
+    assert_eq!("(1 + 2) * 3", normalized("((1 + 2)) * 3"));
+    assert_eq!("1 - (2 - 3)", normalized("1 - (2 - 3)"));
+    assert_eq!("1 - 2 - 3", normalized("(1 - 2) - 3"));
+    assert_eq!("2 ^ 3 ^ 2", normalized("2 ** (3 ^ 2)"));
+    assert_eq!("(2 ^ 3) ^ 2", normalized("(2 ^ 3) ^ 2"));
+    assert_eq!("-(x ^ 2)", normalized("-(x^2)"));
+    assert_eq!("-x ^ 2", normalized("(-x)^2"));
+    assert_eq!("(-3)!", normalized("(-3)!"));
+    assert_eq!("not (a and b) or d", normalized("not (a and b) or (d)"));
+    assert_eq!("f = (a, b) -> max(a, b) * 2", normalized("f = (a, b) -> (max(a, b) * 2)"));
+    assert_eq!("map(x -> x + 1, [1, 2])", normalized("map(x -> (x + 1), [1, 2])"));
+
+    // printing and parsing again gives the same value
+    let mut calculator = Calculator::new();
+    for input in ["2 - (3 - 4) * -2 ^ 2", "(1 - 2) / (3 / 4) % 5", "-(2 ^ 0.5)!", "1 < 2 == (3 > 4)"].iter() {
+        let printed = calculator.normalize(input).unwrap();
+        assert_eq!(calculator.evaluate_expression(input), calculator.evaluate_expression(&printed));
+    }
+}
+
+#[test]
+fn test_integrate() {
+    let mut calculator = Calculator::new();
+    let mut integral = |input: &str| calculator.evaluate_expression(&format!("integrate({})[0]", input)).unwrap();
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    assert!(close(integral("x^2, x, 0, 1"), 1.0 / 3.0));
+    assert!(close(integral("sin(x), x, 0, pi"), 2.0));
+    assert!(close(integral("sin(x), x, pi, 0"), -2.0));
+    assert!(close(integral("1 / sqrt(x), x, 0, 4"), 4.0));
+    assert!(close(integral("exp(-x), x, 0, 1/0"), 1.0));
+    assert!(close(integral("exp(-(x^2)), x, -1/0, 1/0"), std::f64::consts::PI.sqrt()));
+    assert!(close(integral("1 / x^2, x, -1/0, -1"), 1.0));
+    assert!(close(integral("sum(k, 1, 3, x^k), x, 0, 1, 10^-12"), 1.0 / 2.0 + 1.0 / 3.0 + 1.0 / 4.0));
+
+    // the integration variable is bound locally
+    calculator.evaluate_expression("x = 7").unwrap();
+    let result = calculator.evaluate("integrate(x, x, 0, 2)").unwrap();
+    let items = result.to_list().unwrap();
+    assert!(close(items[0].to_number().unwrap(), 2.0));
+    assert!(items[1].to_number().unwrap() < 1e-9);
+    assert_eq!(Ok(7.0), calculator.evaluate_expression("x"));
+
+    assert!(calculator.evaluate("integrate(1 / x, x, -1, 1)").is_err());
+    assert!(calculator.evaluate("integrate(x, x, 0, 1, 0)").is_err());
+}
+
+#[test]
+fn test_index() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate("v = [10, [20, 30], 40]").unwrap();
+    assert_eq!(Ok(10.0), calculator.evaluate_expression("v[0]"));
+    assert_eq!(Ok(30.0), calculator.evaluate_expression("v[1][1]"));
+    assert_eq!(Ok(-39.0), calculator.evaluate_expression("1 - v[1 + 1]"));
+    assert_eq!(Ok(3.0), calculator.evaluate_expression("[1, 2, 3][2]"));
+    assert_eq!("v[1][0] * 2", calculator.normalize("(v[1])[0] * 2").unwrap());
+    assert!(calculator.evaluate_expression("v[3]").is_err());
+    assert!(calculator.evaluate_expression("v[0.5]").is_err());
+    assert!(calculator.evaluate_expression("v[0, 1]").is_err());
+}
+
+#[test]
+fn test_solve() {
+    let mut calculator = Calculator::new();
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    assert!(close(2.0945514815423265, calculator.evaluate_expression("solve(x^3 - 2x - 5 = 0, x)").unwrap()));
+    assert!(close(2f64.sqrt(), calculator.evaluate_expression("solve(x^2 = 2, x, 1)").unwrap()));
+    assert!(close(std::f64::consts::PI, calculator.evaluate_expression("root(sin(x), x, 3, 4)").unwrap()));
+    assert!(close(0.7390851332151607, calculator.evaluate_expression("root(cos(x) - x, x, 1)").unwrap()));
+    assert_eq!(Ok(Value::List(vec![Value::Number(-1.0), Value::Number(1.0)])), calculator.evaluate("solve(x^2 - 1, x, -5, 5)"));
+    let touching = calculator.evaluate_expression("solve((x - 1)^2, x, -5, 5)[0]").unwrap();
+    assert!((touching - 1.0).abs() < 1e-4);
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("len(solve(x^2 + 1, x, -5, 5))"));
+    assert!(calculator.evaluate_expression("root(x^2 + 1, x, 0)").is_err());
+    assert!(calculator.evaluate_expression("root(x - 5, x, 0, 1)").is_err());
+    calculator.evaluate("x = 7").unwrap();
+    calculator.evaluate("solve(x = 3, x)").unwrap();
+    assert_eq!(Ok(7.0), calculator.evaluate_expression("x"));
+    assert!(calculator.evaluate_expression("2 * x = 3").is_err());
+    assert_eq!(Ok(14.0), calculator.evaluate_expression("2x"));
+    assert!(calculator.evaluate_expression("solve(x = x + 1, x)").is_err());
+    assert!(calculator.evaluate_expression("solve(x - (x + 1), x, 1e17)").is_err());
+    assert_eq!(Ok(1e17), calculator.evaluate_expression("solve(x = 1e17, x)"));
+
+    // scientific notation is a number, not a product with e
+    assert_eq!(Ok(200000.0), calculator.evaluate_expression("2e5"));
+    assert_eq!(Ok(1500.0), calculator.evaluate_expression("1.5E3"));
+    assert_eq!(Ok(1e-20), calculator.evaluate_expression("1e-20"));
+    assert_eq!(Ok(2.5e+7), calculator.evaluate_expression("2.5e+7"));
+    assert_eq!(Ok(2.0 * std::f64::consts::E), calculator.evaluate_expression("2e"));
+    assert_eq!(Ok(2.0 * std::f64::consts::E - 3.0), calculator.evaluate_expression("2e - 3"));
+    assert_eq!(Ok(0.002 * 7.0), calculator.evaluate_expression("2e-3x"));
+    assert!(calculator.evaluate_expression("sum(k, 1, 1e9, k)").is_err());
+}
+
+#[test]
+fn test_polynomials() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate("p = poly([1, -3, 2])").unwrap();
+    assert_eq!("x^2 - 3x + 2", calculator.evaluate("p").unwrap().to_string());
+    assert_eq!("-2x^3 + 0.5x - 1", calculator.evaluate("poly([-2, 0, 0.5, -1])").unwrap().to_string());
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("p(2)"));
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("degree(p)"));
+    assert_eq!(Ok(calculator.evaluate("p").unwrap()), calculator.evaluate("poly((x - 1)*(x - 2), x)"));
+    calculator.evaluate("a = 3").unwrap();
+    assert_eq!("3x^2 + 2x", calculator.evaluate("poly(a*x^2 + 2x, x)").unwrap().to_string());
+    assert!(calculator.evaluate("poly(sin(x), x)").is_err());
+
+    assert_eq!("x^3 - 2x^2 - 3x + 2", calculator.evaluate("p * poly([1, 1]) - 2 * poly([1, 0])").unwrap().to_string());
+    assert_eq!("x^4 - 6x^3 + 13x^2 - 12x + 4", calculator.evaluate("p^2").unwrap().to_string());
+    assert_eq!("[x - 1, 0]", calculator.evaluate("polydiv(p, poly([1, -2]))").unwrap().to_string());
+    assert_eq!("[x - 2, 3]", calculator.evaluate("polydiv(p + 3, poly([1, -1]))").unwrap().to_string());
+    assert_eq!("x - 2", calculator.evaluate("polygcd(p, poly([1, 1, -6]))").unwrap().to_string());
+    assert_eq!("2x - 3", calculator.evaluate("polyder(p)").unwrap().to_string());
+    assert_eq!("x - 1", calculator.evaluate("p / poly([1, -2])").unwrap().to_string());
+    assert!(calculator.evaluate("p / poly([1, 5])").is_err());
+    assert_eq!("[1, -3, 2]", calculator.evaluate("coeffs(p)").unwrap().to_string());
+
+    let close = |value: &Value, re: f64, im: f64| {
+        let pair = value.to_list().unwrap();
+        (pair[0].to_number().unwrap() - re).abs() < 1e-12 && (pair[1].to_number().unwrap() - im).abs() < 1e-12
+    };
+    let roots = calculator.evaluate("roots(p^2 * poly([1, 0, 4]))").unwrap();
+    let roots = roots.to_list().unwrap();
+    assert_eq!(6, roots.len());
+    assert!(close(&roots[0], 0.0, -2.0) && close(&roots[1], 0.0, 2.0));
+    // repeated roots are only found to about half the precision
+    let real = |value: &Value| value.to_list().unwrap()[0].to_number().unwrap();
+    assert!((real(&roots[2]) - 1.0).abs() < 1e-6 && (real(&roots[5]) - 2.0).abs() < 1e-6);
+    let roots = calculator.evaluate("roots(p)").unwrap();
+    assert!(close(&roots.to_list().unwrap()[0], 1.0, 0.0) && close(&roots.to_list().unwrap()[1], 2.0, 0.0));
+    let roots = calculator.evaluate("roots(poly([1, 0, 1]))").unwrap();
+    let roots = roots.to_list().unwrap();
+    assert_eq!(2, roots.len());
+    let imaginary = roots[1].to_list().unwrap()[1].to_number().unwrap();
+    assert!((imaginary - 1.0).abs() < 1e-12);
+    assert!(calculator.evaluate("roots(poly([0]))").is_err());
+    assert!(calculator.evaluate("roots(poly([1, inf]))").is_err());
+    assert!(calculator.evaluate("roots(poly([nan, 1]))").is_err());
+    assert!(calculator.evaluate("roots(poly([10^-300, 10^300]))").is_err());
+}
+
+#[test]
+fn test_ode() {
+    let mut calculator = Calculator::new();
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-7;
+    assert!(close(1f64.exp(), calculator.evaluate_expression("ode((t, y) -> y, 0, 1, 1)").unwrap()));
+    assert!(close(0.5, calculator.evaluate_expression("ode((t, y) -> t, 0, 0, 1)").unwrap()));
+    assert!(close((-2f64).exp(), calculator.evaluate_expression("ode((t, y) -> -y, 1, 1, 3)").unwrap()));
+    assert!(close(1.0, calculator.evaluate_expression("ode((t, y) -> -y, 3, exp(-3), 0)").unwrap()));
+
+    // harmonic oscillator as a system of two equations
+    calculator.evaluate("state = ode((t, y) -> [y[1], -y[0]], 0, [1, 0], pi)").unwrap();
+    assert!(close(-1.0, calculator.evaluate_expression("state[0]").unwrap()));
+    assert!(close(0.0, calculator.evaluate_expression("state[1]").unwrap()));
+
+    calculator.evaluate("table = ode((t, y) -> [y[1], -y[0]], 0, [1, 0], 2, 5)").unwrap();
+    assert_eq!(Ok(5.0), calculator.evaluate_expression("len(table)"));
+    assert_eq!(Ok(3.0), calculator.evaluate_expression("len(table[4])"));
+    assert_eq!(Ok(1.5), calculator.evaluate_expression("table[3][0]"));
+    assert!(close(1.5f64.cos(), calculator.evaluate_expression("table[3][1]").unwrap()));
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("table[0][1]"));
+
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("ode((t, y) -> y, 1, 2, 1)"));
+    assert!(calculator.evaluate_expression("ode((t, y) -> y^2, 0, 1, 2)").is_err());
+    assert!(calculator.evaluate("ode((t, y) -> [y[0]], 0, [1, 2], 1)").is_err());
+    assert!(calculator.evaluate("ode((t, y) -> y, 0, 1, 1, 1)").is_err());
+}
+
+#[test]
+fn test_minimize() {
+    let mut calculator = Calculator::new();
+    let close = |value: &Value, expected: f64, tolerance: f64| (value.to_number().unwrap() - expected).abs() < tolerance;
+
+    let result = calculator.evaluate("minimize((x - 2)^2 + 1, x, 0, 5)").unwrap();
+    let result = result.to_list().unwrap();
+    assert!(close(&result[0], 2.0, 1e-7) && close(&result[1], 1.0, 1e-12));
+
+    let result = calculator.evaluate("maximize(sin(x), x, 0, 3)").unwrap();
+    let result = result.to_list().unwrap();
+    assert!(close(&result[0], std::f64::consts::FRAC_PI_2, 1e-7) && close(&result[1], 1.0, 1e-12));
+
+    // Rosenbrock's valley
+    calculator.evaluate("x = 7").unwrap();
+    let result = calculator.evaluate("minimize((1 - x)^2 + 100(y - x^2)^2, [x, y], [-1.2, 1])").unwrap();
+    let result = result.to_list().unwrap();
+    let argmin = result[0].to_list().unwrap();
+    assert!(close(&argmin[0], 1.0, 1e-4) && close(&argmin[1], 1.0, 1e-4) && close(&result[1], 0.0, 1e-8));
+    assert_eq!(Ok(7.0), calculator.evaluate_expression("x"));
+
+    assert!(calculator.evaluate("minimize((1 - x)^2 + 100(y - x^2)^2, [x, y], [-1.2, 1], 10^-10, 5)").is_err());
+    assert!(calculator.evaluate("minimize(x^2, [x, y], [1])").is_err());
+    assert!(calculator.evaluate("minimize(x^2, x, 1)").is_err());
+    assert!(calculator.evaluate("minimize(x^2, x, -1, 1, 0)").is_err());
+    assert!(calculator.evaluate("minimize(x^2, 2, -1, 1)").is_err());
+    assert!(calculator.evaluate("minimize(nan, x, 0, 1)").is_err());
+    assert!(calculator.evaluate("maximize(sqrt(x), x, -1, 1)").is_err());
+    assert!(calculator.evaluate("minimize(x + nan * y, [x, y], [0, 0])").is_err());
+}
+
+#[test]
+fn test_math_functions() {
+    let mut calculator = Calculator::new();
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+    assert!(close(std::f64::consts::FRAC_PI_2, calculator.evaluate_expression("asin(1)").unwrap()));
+    assert!(close(std::f64::consts::PI, calculator.evaluate_expression("acos(-1)").unwrap()));
+    assert!(close(std::f64::consts::FRAC_PI_4, calculator.evaluate_expression("atan(1)").unwrap()));
+    assert!(close(-3.0 * std::f64::consts::FRAC_PI_4, calculator.evaluate_expression("atan2(-1, -1)").unwrap()));
+    assert!(close(1.0, calculator.evaluate_expression("cosh(2)^2 - sinh(2)^2").unwrap()));
+    assert!(close(0.5, calculator.evaluate_expression("tanh(atanh(0.5))").unwrap()));
+    assert!(close(3.0, calculator.evaluate_expression("acosh(cosh(3)) * asinh(sinh(1))").unwrap()));
+
+    assert_eq!(Ok(-3.0), calculator.evaluate_expression("floor(-2.5)"));
+    assert_eq!(Ok(-2.0), calculator.evaluate_expression("ceil(-2.5)"));
+    assert_eq!(Ok(-3.0), calculator.evaluate_expression("round(-2.5)"));
+    assert_eq!(Ok(1.235), calculator.evaluate_expression("round(1.23456, 3)"));
+    assert_eq!(Ok(1200.0), calculator.evaluate_expression("round(1234.5, -2)"));
+    assert_eq!(Ok(-2.0), calculator.evaluate_expression("trunc(-2.75)"));
+    assert_eq!(Ok(-0.75), calculator.evaluate_expression("frac(-2.75)"));
+    assert_eq!(Ok(5.0), calculator.evaluate_expression("hypot(3, 4)"));
+    assert_eq!(Ok(-3.0), calculator.evaluate_expression("cbrt(-27)"));
+    assert_eq!(Ok(-2.0), calculator.evaluate_expression("nthroot(-32, 5)"));
+    assert_eq!(Ok(3.0), calculator.evaluate_expression("nthroot(81, 4)"));
+    assert!(close(3.0, calculator.evaluate_expression("log(8, 2)").unwrap()));
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("log(100)"));
+
+    assert_eq!(Ok(6.0), calculator.evaluate_expression("gcd(-12, 18)"));
+    assert_eq!(Ok(36.0), calculator.evaluate_expression("lcm(12, -18)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("lcm(0, 5)"));
+    assert_eq!(Ok(10.0), calculator.evaluate_expression("nCr(5, 2)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("nCr(5, 7)"));
+    assert!(close(1.0, calculator.evaluate_expression("nCr(100, 50)").unwrap() / 1.0089134454556419e29));
+    assert_eq!(Ok(20.0), calculator.evaluate_expression("nPr(5, 2)"));
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("clamp(5, -2, 2)"));
+    assert_eq!(Ok(7.5), calculator.evaluate_expression("lerp(5, 10, 0.5)"));
+
+    // undefined results are errors rather than NaN, poles give infinities
+    assert_eq!(Err(String::from("sqrt(-1) is undefined")), calculator.evaluate_expression("sqrt(-1)"));
+    assert_eq!(Err(String::from("(-1) ^ 0.5 is undefined")), calculator.evaluate_expression("(-1)^0.5"));
+    assert!(calculator.evaluate_expression("pow(-8, 1/3)").is_err());
+    assert!(calculator.evaluate_expression("5 % 0").is_err());
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("round(123, -400)"));
+    assert_eq!(Ok(123.0), calculator.evaluate_expression("round(123, 400)"));
+    assert!(calculator.evaluate_expression("asin(2)").is_err());
+    assert!(calculator.evaluate_expression("acosh(0.5)").is_err());
+    assert!(calculator.evaluate_expression("nthroot(-16, 4)").is_err());
+    assert!(calculator.evaluate_expression("log(5, 1)").is_err());
+    assert!(calculator.evaluate_expression("gcd(1.5, 3)").is_err());
+    assert!(calculator.evaluate_expression("nCr(-5, 2)").is_err());
+    assert!(calculator.evaluate_expression("round(1, 0.5)").is_err());
+    assert!(calculator.evaluate_expression("clamp(1, 2, -2)").is_err());
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("atanh(1)"));
+    assert_eq!(Ok(f64::NEG_INFINITY), calculator.evaluate_expression("ln(0)"));
+
+    assert!(close(1.0 / 2f64.sqrt(), calculator.evaluate_expression("diff(asin(x), x, 0.5^0.5) * 0.5").unwrap()));
+    assert!(close(1.0 / (2.0 * 10f64.ln()), calculator.evaluate_expression("diff(log(x), x, 2)").unwrap()));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("diff(floor(x), x, 2.5)"));
+}
+
+#[test]
+fn test_gamma() {
+    let mut calculator = Calculator::new();
+    let close = |a: f64, b: f64| ((a - b) / b).abs() < 1e-13;
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("0!"));
+    assert_eq!(Ok(3628800.0), calculator.evaluate_expression("10!"));
+    assert_eq!(Ok(2432902008176640000.0), calculator.evaluate_expression("20!"));
+    assert!(close(3.323350970447843, calculator.evaluate_expression("2.5!").unwrap()));
+    assert!(close(std::f64::consts::PI.sqrt(), calculator.evaluate_expression("gamma(0.5)").unwrap()));
+    assert!(close(-3.5449077018110318, calculator.evaluate_expression("gamma(-0.5)").unwrap()));
+    assert!(close(7.257415615307994e306, calculator.evaluate_expression("170!").unwrap()));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("171!"));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("gamma(200)"));
+    assert!(calculator.evaluate_expression("(-1)!").is_err());
+    assert!(calculator.evaluate_expression("gamma(-2)").is_err());
+
+    assert!(close(857.9336698258574, calculator.evaluate_expression("lgamma(200)").unwrap()));
+    assert!(close(1.265512123484645, calculator.evaluate_expression("lgamma(-0.5)").unwrap()));
+    assert!(close(1.0 / 20.0, calculator.evaluate_expression("beta(2, 4)").unwrap()));
+    let large = calculator.evaluate_expression("beta(150, 160)").unwrap();
+    assert!((large / 1.6061158879580881e-94 - 1.0).abs() < 1e-10);
+    assert!(close(-0.5772156649015329, calculator.evaluate_expression("digamma(1)").unwrap()));
+    assert!((0.03648997397857652 - calculator.evaluate_expression("digamma(-0.5)").unwrap()).abs() < 1e-13);
+    assert!(close(2.0 * (1.5 - 0.5772156649015329), calculator.evaluate_expression("diff(x!, x, 2)").unwrap()));
+}
+
+#[test]
+fn test_distributions() {
+    let mut calculator = Calculator::new();
+    let mut check = |input: &str, expected: f64| {
+        let value = calculator.evaluate_expression(input).unwrap();
+        assert!((value - expected).abs() <= 1e-12 * expected.abs().max(1.0), "{} = {}, expected {}", input, value, expected);
+    };
+    check("erf(0.5)", 0.5204998778130465);
+    check("erf(-2)", -0.9953222650189527);
+    check("erfc(3)", 2.209049699858544e-05);
+    check("erfc(-1)", 1.8427007929497148);
+
+    check("normpdf(0)", 0.3989422804014327);
+    check("normpdf(12, 10, 2)", 0.12098536225957168);
+    check("normcdf(1.96)", 0.9750021048517795);
+    check("normcdf(-10)", 7.619853024160526e-24);
+    check("norminv(0.975)", 1.9599639845400542);
+    check("norminv(10^-10, 5, 2)", -7.722681804808112);
+    check("normcdf(norminv(0.3))", 0.3);
+
+    check("tpdf(1, 3)", 0.20674833578317203);
+    check("tcdf(2, 5)", 0.9490302605850709);
+    check("tinv(0.975, 10)", 2.228138851986275);
+    check("chi2pdf(2, 3)", 0.2075537487102974);
+    check("chi2cdf(3.84, 1)", 0.949956478751295);
+    check("chi2inv(0.95, 4)", 9.487729036781157);
+    check("fpdf(1, 5, 10)", 0.4954797834866387);
+    check("fcdf(2, 3, 7)", 0.797306357513349);
+    check("finv(0.95, 2, 12)", 3.885293834652394);
+
+    check("binompdf(3, 10, 0.5)", 0.1171875);
+    check("binomcdf(3, 10, 0.5)", 0.171875);
+    check("binominv(0.5, 10, 0.3)", 3.0);
+    check("poisspdf(2, 3)", 0.22404180765538775);
+    check("poisscdf(2, 3)", 0.42319008112684353);
+    check("poissinv(0.9, 3)", 5.0);
+    check("exppdf(1, 2)", 0.2706705664732254);
+    check("expcdf(1)", 0.6321205588285577);
+    check("expinv(0.5, 2)", 0.34657359027997264);
+    check("unifpdf(3, 2, 6)", 0.25);
+    check("unifcdf(3, 2, 6)", 0.25);
+    check("unifinv(0.25)", 0.25);
+
+    assert!(calculator.evaluate_expression("normpdf(0, 0, -1)").is_err());
+    assert!(calculator.evaluate_expression("norminv(1.5)").is_err());
+    assert!(calculator.evaluate_expression("binompdf(1, 2.5, 0.5)").is_err());
+    assert!(calculator.evaluate_expression("unifcdf(0, 1, 1)").is_err());
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("binompdf(11, 10, 0.5)"));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("norminv(1)"));
+
+    // the ends of the support are exact rather than wherever a search stopped
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("tinv(1, 3)"));
+    assert_eq!(Ok(f64::NEG_INFINITY), calculator.evaluate_expression("tinv(0, 3)"));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("chi2inv(1, 3)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("chi2inv(0, 3)"));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("finv(1, 2, 3)"));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("poissinv(1, 3)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("poissinv(1, 0)"));
+    assert_eq!(Ok(1000.0), calculator.evaluate_expression("binominv(1, 1000, 0.01)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("binominv(1, 10, 0)"));
+}
+
+#[test]
+fn test_random() {
+    let mut calculator = Calculator::new();
+    calculator.seed(42);
+    let first: Vec<f64> = (0..5).map(|_| calculator.evaluate_expression("rand()").unwrap()).collect();
+    calculator.evaluate("seed(42)").unwrap();
+    let second: Vec<f64> = (0..5).map(|_| calculator.evaluate_expression("rand()").unwrap()).collect();
+    assert_eq!(first, second);
+    assert!(first.iter().all(|&x| (0.0..1.0).contains(&x)));
+    assert!(first.windows(2).all(|pair| pair[0] != pair[1]));
+
+    let mut seen = [false; 6];
+    for _ in 0..200 {
+        let x = calculator.evaluate_expression("randint(-2, 3)").unwrap();
+        assert!(x.fract() == 0.0 && (-2.0..=3.0).contains(&x));
+        seen[(x + 2.0) as usize] = true;
+    }
+    assert!(seen.iter().all(|&s| s));
+
+    // mean of many normal draws
+    let mean = calculator.evaluate_expression("sum(i, 1, 10000, randn(5, 2)) / 10000").unwrap();
+    assert!((mean - 5.0).abs() < 0.1);
+    assert_eq!(Ok(3.0), calculator.evaluate_expression("randn(3, 0)"));
+    assert_eq!(Ok(7.0), calculator.evaluate_expression("choice([7, 7])"));
+    assert!(calculator.evaluate_expression("choice([])").is_err());
+    assert!(calculator.evaluate_expression("randint(3, 1)").is_err());
+    assert!(calculator.evaluate_expression("randint(0.5, 1)").is_err());
+
+    // random calls are not folded or cancelled
+    assert_eq!("rand() - rand()", calculator.evaluate("simplify(rand() - rand(), x)").unwrap().to_string().trim_start_matches("x -> "));
+    assert_eq!("2 * randint(1, 2) + 1", calculator.evaluate("simplify(2 * randint(1, 2) + 1, x)").unwrap().to_string().trim_start_matches("x -> "));
+}
+
+#[test]
+fn test_finance() {
+    let mut calculator = Calculator::new();
+    let mut check = |input: &str, expected: f64| {
+        let value = calculator.evaluate_expression(input).unwrap();
+        assert!((value - expected).abs() <= 1e-9 * expected.abs().max(1.0), "{} = {}, expected {}", input, value, expected);
+    };
+    // the examples from spreadsheet documentation
+    check("pmt(0.05/12, 360, 200000)", -1073.6432460242797);
+    check("pmt(0, 10, 1000)", -100.0);
+    check("fv(0.06/12, 10, -200, -500, 1)", 2581.4033740601367);
+    check("pv(0.08/12, 240, 500)", -59777.14585118777);
+    check("nper(0.01, -100, -1000, 10000, 1)", 59.67386567429457);
+    check("rate(48, -200, 8000)", 0.007701472488201711);
+    check("npv(0.1, [-10000, 3000, 4200, 6800])", 1188.4434123352216);
+    check("irr([-70000, 12000, 15000, 18000, 21000])", -0.021244848273411);
+    check("irr([-70000, 12000, 15000, 18000, 21000, 26000])", 0.08663094803653154);
+    check("xirr([-10000, 2750, 4250, 3250, 2750], [39448, 39508, 39751, 39859, 39904])", 0.37336253351883136);
+
+    check("len(amortize(1000, 0.01, 3))", 3.0);
+    check("amortize(1000, 0.01, 3)[0][2]", 10.0);
+    check("amortize(1000, 0.01, 3)[2][4]", 0.0);
+    check("sum_over(row -> row[3], amortize(1000, 0.01, 3))", 1000.0);
+
+    assert!(calculator.evaluate_expression("irr([1, 2])").is_err());
+    assert!(calculator.evaluate_expression("rate(10, 0, 100)").is_err());
+    assert_eq!(Err(String::from("nper(0, 0, 100) is undefined")), calculator.evaluate_expression("nper(0, 0, 100)"));
+    assert!(calculator.evaluate_expression("pv(0.1, 10, 100, 0, 2)").is_err());
+    assert!(calculator.evaluate_expression("xirr([-1, 2], [0])").is_err());
+    assert!(calculator.evaluate("amortize(1000, 0.01, 2.5)").is_err());
+}
+
+#[test]
+fn test_number_theory() {
+    let mut calculator = Calculator::new();
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("isprime(9007199254740881)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("isprime(561)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("isprime(1)"));
+    assert_eq!(Ok(9007199254740881.0), calculator.evaluate_expression("nextprime(9007199254740847)"));
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("nextprime(-5)"));
+    assert_eq!("[6361, 69431, 20394401]", calculator.evaluate("factor(2^53 - 1)").unwrap().to_string());
+    assert_eq!("[67108837, 67108859]", calculator.evaluate("factor(4503597479886983)").unwrap().to_string());
+    assert_eq!("[2, 2, 2, 3, 3, 5]", calculator.evaluate("factor(360)").unwrap().to_string());
+    assert_eq!("[]", calculator.evaluate("factor(1)").unwrap().to_string());
+    assert_eq!("[1, 2, 3, 4, 6, 9, 12, 18, 36]", calculator.evaluate("divisors(36)").unwrap().to_string());
+    assert_eq!(Ok(960.0), calculator.evaluate_expression("totient(3600)"));
+
+    // exact where the floating point power would have lost every digit
+    assert_eq!(Ok(136318165.0), calculator.evaluate_expression("modpow(3, 200, 1000000007)"));
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("modpow(-1, 3, 3)"));
+    assert_eq!(Ok(2753.0), calculator.evaluate_expression("modinv(17, 3120)"));
+    assert_eq!(Err(String::from("6 has no inverse modulo 9")), calculator.evaluate_expression("modinv(6, 9)"));
+    assert_eq!("[2, -9, 47]", calculator.evaluate("egcd(240, 46)").unwrap().to_string());
+
+    assert_eq!(Err(String::from("factor(0) is undefined")), calculator.evaluate_expression("factor(0)"));
+    assert!(calculator.evaluate_expression("isprime(2.5)").is_err());
+    assert!(calculator.evaluate_expression("modpow(2, -1, 5)").is_err());
+
+    // beyond 2^53 the arguments may already be rounded and results may not fit
+    assert_eq!(Err(String::from("isprime(2305843009213694000) is undefined")), calculator.evaluate_expression("isprime(2^61 - 1)"));
+    assert!(calculator.evaluate_expression("factor(2^63 - 25)").is_err());
+    assert!(calculator.evaluate_expression("egcd(2^60, 3)").is_err());
+    assert!(calculator.evaluate_expression("nextprime(2^53)").is_err());
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("isprime(2^53 - 111)"));
+
+    // integer literals and words are exact up to 64 bits
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("isprime(2305843009213693951)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("isprime(18446744073709551615)"));
+    assert!(calculator.evaluate_expression("isprime(18446744073709551616)").is_err());
+    assert_eq!("[1000000007, 1000000009]", calculator.evaluate("factor(1000000016000000063)").unwrap().to_string());
+    assert!(calculator.evaluate_expression("factor(2305843009213693953)").is_err());
+    assert_eq!(Ok(2305843009213693952.0), calculator.evaluate_expression("2305843009213693951 + 0"));
+    calculator.evaluate("word(64, 0)").unwrap();
+    assert_eq!("1", calculator.evaluate("isprime(2^61 - 1)").unwrap().to_string());
+    assert_eq!("[1000000007, 1000000009]", calculator.evaluate("factor(1000000007 * 1000000009)").unwrap().to_string());
+    assert_eq!("2305843009213693967", calculator.evaluate("nextprime(2^61)").unwrap().to_string());
+    assert_eq!("[3, 768614336404564651]", calculator.evaluate("factor(2^61 + 1)").unwrap().to_string());
+    calculator.evaluate("word(0)").unwrap();
+}
+
+#[test]
+fn test_programmer_mode() {
+    let mut calculator = Calculator::new();
+    // outside of programmer mode the operators work on 64-bit integers
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("6 & 3"));
+    assert_eq!(Ok(11.0), calculator.evaluate_expression("7 & 3 | 8"));
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("5 xor 1 == 4"));
+    assert_eq!(Ok(-1.0), calculator.evaluate_expression("-1 >> 1"));
+    assert_eq!(Ok(255.0), calculator.evaluate_expression("0xff"));
+    assert_eq!(Ok(10.0), calculator.evaluate_expression("0b1010"));
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("1 && 1"));
+    assert!(calculator.evaluate_expression("2.5 & 1").is_err());
+
+    calculator.evaluate("word(8, 0)").unwrap();
+    assert_eq!("0", calculator.evaluate("0xff + 1").unwrap().to_string());
+    assert_eq!("240", calculator.evaluate("~0x0f").unwrap().to_string());
+    assert_eq!("3", calculator.evaluate("rotl(0x81, 1)").unwrap().to_string());
+    assert_eq!("192", calculator.evaluate("rotr(0x81, 1)").unwrap().to_string());
+    assert_eq!("255", calculator.evaluate("0 - 1").unwrap().to_string());
+    assert_eq!("3", calculator.evaluate("10 / 3").unwrap().to_string());
+    assert_eq!("5", calculator.evaluate("sqrt(16) + 1").unwrap().to_string());
+    assert!(calculator.evaluate("sqrt(2) + 1").is_err());
+
+    match calculator.evaluate("0x5a").unwrap() {
+        Value::Word(word) => {
+            assert_eq!("0x5a", word.hex());
+            assert_eq!("0b0101_1010", word.binary());
+        },
+        value => panic!("expected an integer but found {}", value),
+    }
+
+    calculator.evaluate("word(8)").unwrap();
+    assert_eq!("-128", calculator.evaluate("127 + 1").unwrap().to_string());
+    assert_eq!("-32", calculator.evaluate("-128 >> 2").unwrap().to_string());
+
+    calculator.evaluate("word(8, 1, 1)").unwrap();
+    assert_eq!("-128", calculator.evaluate("-128").unwrap().to_string());
+    assert_eq!(Err(String::from("127 + 1 overflows i8")), calculator.evaluate("127 + 1").map(|value| value.to_string()));
+    assert!(calculator.evaluate("-128 / -1").is_err());
+    assert!(calculator.evaluate("2^7").is_err());
+    assert_eq!("-128", calculator.evaluate("(-2)^7").unwrap().to_string());
+
+    // integers beyond the precision of a float stay exact
+    calculator.evaluate("word(64, 0)").unwrap();
+    assert_eq!("18446744073709551613", calculator.evaluate("0xFFFF_FFFF_FFFF_FFFF * 3").unwrap().to_string());
+    calculator.evaluate("word(128, 0, 1)").unwrap();
+    assert_eq!("340282366920938463463374607431768211455", calculator.evaluate("2^127 - 1 + 2^127").unwrap().to_string());
+    assert!(calculator.evaluate("2^128").is_err());
+
+    assert!(calculator.evaluate("word(12)").is_err());
+    calculator.evaluate("word(0)").unwrap();
+    assert_eq!(Ok(0.5), calculator.evaluate_expression("1 / 2"));
+}
+
+#[test]
+fn test_format() {
+    let mut format = Format::default();
+    assert_eq!("0.30000000000000004", format.number(0.1 + 0.2));
+    assert_eq!("1000000", format.number(1e6));
+
+    format.precision = format::Precision::Significant(15);
+    assert_eq!("0.3", format.number(0.1 + 0.2));
+    assert_eq!("666666.666666667", format.number(2e6 / 3.0));
+    assert_eq!("120000", Format{ precision: format::Precision::Significant(2), ..Format::default() }.number(123456.0));
+    assert_eq!("0.00012", Format{ precision: format::Precision::Significant(2), ..Format::default() }.number(0.00012345));
+
+    format.notation = format::Notation::Scientific;
+    assert_eq!("1.23456e5", format.number(123456.0));
+    format.notation = format::Notation::Engineering;
+    assert_eq!("123.456e3", format.number(123456.0));
+    assert_eq!("470e-6", format.number(0.00047));
+    format.notation = format::Notation::Si;
+    assert_eq!("4.7k", format.number(4700.0));
+    assert_eq!("-2.2µ", format.number(-0.0000022));
+    assert_eq!("1e33", format.number(1e33));
+
+    let format = Format{ precision: format::Precision::Fixed(2), separator: Some(','), ..Format::default() };
+    assert_eq!("1,234,567.89", format.number(1234567.891));
+    assert_eq!("-1,234.50", format.number(-1234.5));
+    assert_eq!("123.00", format.number(123.0));
+
+    let format = Format{ fraction: true, ..Format::default() };
+    assert_eq!("10/21", format.number(1.0 / 3.0 + 1.0 / 7.0));
+    assert_eq!("-5/2", format.number(-2.5));
+    assert_eq!("4", format.number(4.0));
+    assert_eq!(std::f64::consts::PI.to_string(), format.number(std::f64::consts::PI));
+
+    let format = Format{ radix: 16, ..Format::default() };
+    assert_eq!("0xff.8", format.number(255.5));
+    assert_eq!("-0xff", format.number(-255.0));
+    assert_eq!("zz_36", Format{ radix: 36, ..Format::default() }.number(1295.0));
+    assert_eq!("0b1010", Format{ radix: 2, ..Format::default() }.number(10.0));
+    assert_eq!("[0x1, 0x2.8]", format.value(&Value::List(vec![Value::Number(1.0), Value::Number(2.5)])));
+
+    // the settings of the calculator
+    let mut calculator = Calculator::new();
+    calculator.evaluate("format(digits, 15)").unwrap();
+    calculator.evaluate("format(si)").unwrap();
+    assert_eq!(format::Precision::Significant(15), calculator.format.precision);
+    assert_eq!(format::Notation::Si, calculator.format.notation);
+    assert!(calculator.evaluate("format(radix, 37)").is_err());
+    assert!(calculator.evaluate("format(bogus)").is_err());
+    assert!(calculator.evaluate("format(1)").is_err());
+    calculator.evaluate("format(default)").unwrap();
+    assert_eq!(Format::default(), calculator.format);
+}
+
+#[test]
+fn test_angles() {
+    let mut calculator = Calculator::new();
+    assert_eq!(Ok(0.5), calculator.evaluate_expression("sin(30°)"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("cos(90°)"));
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("tan(pi/4)"));
+
+    calculator.evaluate("angle(deg)").unwrap();
+    assert_eq!(Ok(0.5), calculator.evaluate_expression("sin(30)"));
+    assert_eq!(Ok(-1.0), calculator.evaluate_expression("cos(180)"));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("tan(90)"));
+    assert_eq!(Ok(30.0), calculator.evaluate_expression("asin(0.5)"));
+    assert_eq!(Ok(45.0), calculator.evaluate_expression("atan(1)"));
+    assert_eq!(Ok(135.0), calculator.evaluate_expression("atan2(1, -1)"));
+    assert_eq!(Ok(45.0), calculator.evaluate_expression("45°"));
+    let derivative = calculator.evaluate_expression("diff(sin(x), x, 0)").unwrap();
+    assert!((derivative - std::f64::consts::PI / 180.0).abs() < 1e-15);
+
+    calculator.evaluate("angle(grad)").unwrap();
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("sin(100)"));
+    assert_eq!(Ok(50.0), calculator.evaluate_expression("acos(sqrt(2)/2)"));
+    assert_eq!(Ok(100.0), calculator.evaluate_expression("90°"));
+
+    assert!(calculator.evaluate("angle(turns)").is_err());
+    calculator.evaluate("angle(rad)").unwrap();
+    assert_eq!(Ok(std::f64::consts::PI / 6.0), calculator.evaluate_expression("asin(0.5)"));
+}
+
+#[test]
+fn test_constants() {
+    let mut calculator = Calculator::new();
+    assert_eq!(Ok(std::f64::consts::PI), calculator.evaluate_expression("PI"));
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("phi^2 - phi"));
+    assert_eq!(Ok(f64::INFINITY), calculator.evaluate_expression("-(-inf)"));
+    assert!(calculator.evaluate_expression("nan").unwrap().is_nan());
+    assert_eq!(Ok(299792458.0), calculator.evaluate_expression("c"));
+    assert_eq!(Ok(6.62607015e-34), calculator.evaluate_expression("h"));
+    assert!(calculator.evaluate_expression("c = 3").is_err());
+    assert!(calculator.evaluate_expression("h = 1").is_err());
+    assert_eq!(Ok(6.67430e-11), calculator.evaluate_expression("G"));
+
+    // physical constants are case sensitive, so g is still a variable
+    calculator.evaluate_expression("g = 9.81").unwrap();
+    assert_eq!(Ok(9.81), calculator.evaluate_expression("g"));
+    assert!(calculator.evaluate_expression("G = 1").is_err());
+    assert!(calculator.evaluate_expression("pi = 3").is_err());
+    assert!(calculator.evaluate_expression("(Tau=2)").is_err());
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("pi == pi"));
+    assert!(constants::table().contains("k_B"));
+}
+
+#[test]
+fn test_const() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate("1 + 1").unwrap();
+    assert_eq!(Ok(9.81), calculator.evaluate_expression("const g = 9.81"));
+    assert_eq!(Ok(19.62), calculator.evaluate_expression("2g"));
+    assert_eq!(Err(String::from("Cannot assign to g, it was declared const in result [2]")), calculator.evaluate_expression("g = 10"));
+    assert!(calculator.evaluate_expression("const g = 10").is_err());
+    assert_eq!(Ok(9.81), calculator.evaluate_expression("g"));
+    assert_eq!(Err(String::from("Cannot assign to pi, it's a built-in constant")), calculator.evaluate_expression("pi = 3"));
+
+    // parameters of the same name are still bound
+    calculator.evaluate("f = g -> g + 1").unwrap();
+    assert_eq!(Ok(3.0), calculator.evaluate_expression("f(2)"));
+    assert_eq!(Ok(55.0), calculator.evaluate_expression("sum(g, 1, 10, g)"));
+
+    assert!(calculator.evaluate_expression("const 2 = 3").is_err());
+    assert!(calculator.evaluate_expression("const v").is_err());
+    assert_eq!("const v = 2 * x", calculator.normalize("const v = 2x").unwrap());
+
+    // a declaration inside a larger expression is refused, leaving the variable unassigned and free
+    assert!(calculator.evaluate_expression("(const k = 2) + sqrt(-1)").is_err());
+    assert!(!calculator.variables.contains_key("k"));
+    assert!(calculator.evaluate_expression("1 + const k = 2").is_err());
+    assert!(calculator.evaluate_expression("const k = sqrt(-1)").is_err());
+    assert_eq!(Ok(3.0), calculator.evaluate_expression("k = 3"));
+}
+
+#[test]
+fn test_formulas() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate("x = 1").unwrap();
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("y := x + 1"));
+    assert_eq!(Ok(4.0), calculator.evaluate_expression("z := 2y"));
+    calculator.evaluate("x = 5").unwrap();
+    assert_eq!(Ok(6.0), calculator.evaluate_expression("y"));
+    assert_eq!(Ok(12.0), calculator.evaluate_expression("z"));
+    assert_eq!(vec!["y", "z"], calculator.dependents("x"));
+    assert_eq!(vec!["z"], calculator.dependents("y"));
+    assert!(calculator.dependents("z").is_empty());
+
+    // formulas are written out for symbolic operations
+    calculator.evaluate("area := r^2").unwrap();
+    assert_eq!(Ok(6.0), calculator.evaluate_expression("diff(area, r, 3)"));
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("diff(z, x, 0) / 2"));
+
+    // lambda parameters don't leak into formulas
+    calculator.evaluate("f = x -> y").unwrap();
+    assert_eq!(Ok(6.0), calculator.evaluate_expression("f(100)"));
+
+    assert_eq!(Err(String::from("Circular definition, x would depend on itself through x -> z -> y -> x")),
+        calculator.evaluate_expression("x := z"));
+    assert!(calculator.evaluate_expression("w := w + 1").is_err());
+    assert!(calculator.evaluate_expression("2 := 3").is_err());
+    assert!(calculator.evaluate_expression("pi := 3").is_err());
+    assert_eq!(Ok(5.0), calculator.evaluate_expression("x"));
+
+    // assigning a value replaces the formula
+    calculator.evaluate("y = 0").unwrap();
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("z"));
+    assert_eq!(vec!["z"], calculator.dependents("y"));
+    assert!(calculator.dependents("x").is_empty());
+    assert_eq!("y := x + 1", calculator.normalize("y:=x+1").unwrap());
+}
+
+#[test]
+fn test_variable_management() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate("x = 2").unwrap();
+    calculator.evaluate("const g = 9.81").unwrap();
+    calculator.evaluate("y := x^2").unwrap();
+    calculator.evaluate("list = [1, 2]").unwrap();
+    assert_eq!(vec!["g", "list", "x", "y"], calculator.variable_names());
+    let list = calculator.list_variables();
+    assert_eq!(Ok(Value::Number(4.0)), list[3].1);
+    assert_eq!("list", list[1].1.as_ref().unwrap().type_name());
+
+    // formulas follow a renamed variable
+    calculator.rename_variable("x", "side").unwrap();
+    calculator.evaluate("side = 3").unwrap();
+    assert_eq!(Ok(9.0), calculator.evaluate_expression("y"));
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("x"));
+    assert!(calculator.rename_variable("side", "y").is_err());
+    assert!(calculator.rename_variable("side", "sin").is_err());
+    assert!(calculator.rename_variable("side", "pi").is_err());
+    assert!(calculator.rename_variable("nothing", "z").is_err());
+    calculator.rename_variable("g", "gravity").unwrap();
+    assert!(calculator.evaluate_expression("gravity = 1").is_err());
+
+    assert!(calculator.delete_variable("gravity").is_err());
+    calculator.delete_variable("side").unwrap();
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("y"));
+    assert!(calculator.delete_variable("side").is_err());
+
+    calculator.clear_variables();
+    assert!(calculator.variable_names().is_empty());
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("gravity = 1"));
+}
+
+#[test]
+fn test_session() {
+    let mut calculator = Calculator::new();
+    calculator.evaluate("x = 0.1 + 0.2").unwrap();
+    calculator.set_variable("tiny", Value::Number(-5e-324));
+    calculator.set_variable("weird", Value::Number(f64::from_bits(0x7ff8_0000_dead_beef)));
+    calculator.evaluate("values = [inf, -inf, -0, [1, 2]]").unwrap();
+    calculator.evaluate("k = 5").unwrap();
+    calculator.evaluate("f = (t, u) -> t^3 + k*u").unwrap();
+    calculator.evaluate("p = poly([1, -3, 2])").unwrap();
+    calculator.evaluate("const g = 9.81").unwrap();
+    calculator.evaluate("y := x + 1").unwrap();
+    calculator.evaluate("word(16, 1, 1)").unwrap();
+    calculator.evaluate("w = -2").unwrap();
+    calculator.evaluate("word(0)").unwrap();
+
+    let text = session::save(&calculator);
+    assert!(text.contains("nan:7ff80000deadbeef"));
+    assert!(text.contains("var w = i16:check:fffe"));
+    let mut restored = Calculator::new();
+    restored.evaluate("stale = 1").unwrap();
+    session::load(&mut restored, &text).unwrap();
+    assert_eq!(text, session::save(&restored));
+
+    assert_eq!(Some(0.1 + 0.2), restored.get_variable("x").map(|x| x.to_number().unwrap()));
+    assert_eq!((-5e-324f64).to_bits(), restored.get_variable("tiny").unwrap().to_number().unwrap().to_bits());
+    assert_eq!(0x7ff8_0000_dead_beef, restored.get_variable("weird").unwrap().to_number().unwrap().to_bits());
+    assert_eq!((-0f64).to_bits(), restored.evaluate_expression("values[2]").unwrap().to_bits());
+    assert_eq!(Ok(13.0), restored.evaluate_expression("f(2, 1)"));
+    assert_eq!(Ok(0.0), restored.evaluate_expression("p(2)"));
+    assert_eq!(Ok(-2.0), restored.evaluate_expression("w"));
+    assert!(restored.evaluate_expression("g = 1").is_err());
+    restored.evaluate("x = 1").unwrap();
+    assert_eq!(Ok(2.0), restored.evaluate_expression("y"));
+    assert_eq!(Ok(9.81), restored.evaluate_expression("result(6)"));
+    assert_eq!(Ok(0.0), restored.evaluate_expression("stale"));
+
+    // a session that can't be read leaves the calculator as it was
+    assert!(session::load(&mut restored, "version 2\n").is_err());
+    assert!(session::load(&mut restored, "var x = 1\n").is_err());
+    let error = session::load(&mut restored, "version 1\nvar a = 1\nvar sin = 2\n").unwrap_err();
+    assert!(error.starts_with("Line 3 of the session"));
+    assert!(session::load(&mut restored, "version 1\nvar a = [ 1 2\n").is_err());
+    assert!(session::load(&mut restored, "version 1\nformula a = \"1 +\"\n").is_err());
+    assert_eq!(Ok(1.0), restored.evaluate_expression("x"));
+
+    let path = std::env::temp_dir().join(format!("calc-session-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    calculator.save(path).unwrap();
+    let mut loaded = Calculator::new();
+    loaded.load(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(Ok(13.0), loaded.evaluate_expression("f(2, 1)"));
+    assert!(loaded.load(path).is_err());
+}
+
+#[test]
+fn test_commands() {
+    let mut calculator = Calculator::new();
+    let run = |calculator: &mut Calculator, line: &str| run_command(calculator, &[], line);
+
+    run(&mut calculator, ":set angle deg").unwrap();
+    assert_eq!(Ok(0.5), calculator.evaluate_expression("sin(30)"));
+    run(&mut calculator, ":set digits 10").unwrap();
+    assert_eq!(format::Precision::Significant(10), calculator.format.precision);
+    run(&mut calculator, ":set sci").unwrap();
+    assert_eq!(format::Notation::Scientific, calculator.format.notation);
+    run(&mut calculator, ":set word 8 0").unwrap();
+    assert_eq!(Ok(0.0), calculator.evaluate_expression("255 + 1"));
+    run(&mut calculator, ":set word 0").unwrap();
+    assert!(run(&mut calculator, ":set angle turns").is_err());
+    assert!(run(&mut calculator, ":set digits ten").is_err());
+    assert!(run(&mut calculator, ":set word 12").is_err());
+    assert!(run(&mut calculator, ":set").is_err());
+
+    calculator.evaluate("x = 2").unwrap();
+    run(&mut calculator, ":rename x y").unwrap();
+    assert_eq!(Ok(2.0), calculator.evaluate_expression("y"));
+    run(&mut calculator, ":delete Y").unwrap();
+    assert!(calculator.variable_names().is_empty());
+    assert!(run(&mut calculator, ":delete y").is_err());
+
+    assert!(matches!(run(&mut calculator, ":quit"), Ok(Flow::Quit)));
+    assert!(matches!(run(&mut calculator, "  :help"), Ok(Flow::Continue)));
+    assert!(run(&mut calculator, ":bogus").is_err());
+    assert!(run(&mut calculator, ":quit now").is_err());
+
+    calculator.evaluate("const g = 9.81").unwrap();
+    run(&mut calculator, ":reset").unwrap();
+    assert_eq!(Angle::Radians, calculator.angle);
+    assert_eq!(Format::default(), calculator.format);
+    assert!(calculator.results.is_empty());
+    assert_eq!(Ok(1.0), calculator.evaluate_expression("g = 1"));
+}
+
+#[test]
+fn test_console() {
+    // a command ending in what looks like an operator is still a single line
+    let path = std::env::temp_dir().join(format!("calc-console-{}-", std::process::id()));
+    let mut calculator = Calculator::new();
+    let input = format!(":save {}\n1 + 1\n", path.display());
+    console(&mut calculator, &mut input.as_bytes(), None);
+    assert!(path.exists());
+    assert_eq!(vec![Value::Number(2.0)], calculator.results);
+    std::fs::remove_file(path).unwrap();
+
+    // expressions are continued until they're complete, and :quit stops reading
+    let mut calculator = Calculator::new();
+    console(&mut calculator, &mut "(1 +\n2)\n:quit\n3\n".as_bytes(), None);
+    assert_eq!(vec![Value::Number(3.0)], calculator.results);
+
+    // neither are commands ending in a parenthesis nor lines that are only a comment
+    let mut calculator = Calculator::new();
+    console(&mut calculator, &mut ":load missing(\n# a note -\n4\n".as_bytes(), None);
+    assert_eq!(vec![Value::Number(4.0)], calculator.results);
+}
@@ -0,0 +1,662 @@
+use std::fmt;
+
+// most intervals the adaptive quadrature will split the integral into
+const MAX_SUBDIVISIONS: usize = 500;
+
+// 15-point Kronrod nodes on [-1, 1], the odd ones are the nodes of the 7-point Gauss rule
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+type Function<'a> = &'a mut dyn FnMut(f64) -> Result<f64, String>;
+
+fn sample(f: Function, x: f64) -> Result<f64, String> {
+    let y = f(x)?;
+    if !y.is_finite() {
+        return Err(String::from("Integrand is not finite"));
+    }
+    Ok(y)
+}
+
+// integral over [a, b] and the difference between the Kronrod and Gauss estimates
+fn gauss_kronrod(f: Function, a: f64, b: f64) -> Result<(f64, f64), String> {
+    let center = (a + b) / 2.0;
+    let half = (b - a) / 2.0;
+
+    let mid = sample(f, center)?;
+    let mut kronrod = mid * KRONROD_WEIGHTS[7];
+    let mut gauss = mid * GAUSS_WEIGHTS[3];
+    for i in 0..7 {
+        let offset = half * KRONROD_NODES[i];
+        let pair = sample(f, center - offset)? + sample(f, center + offset)?;
+        kronrod += pair * KRONROD_WEIGHTS[i];
+        if i % 2 == 1 {
+            gauss += pair * GAUSS_WEIGHTS[i / 2];
+        }
+    }
+
+    Ok((kronrod * half, ((kronrod - gauss) * half).abs()))
+}
+
+// adaptive quadrature, repeatedly splitting the interval with the largest error estimate
+fn adaptive(f: Function, a: f64, b: f64, tolerance: f64) -> Result<(f64, f64), String> {
+    let (value, error) = gauss_kronrod(f, a, b)?;
+    let mut intervals = vec![(a, b, value, error)];
+
+    loop {
+        let value: f64 = intervals.iter().map(|interval| interval.2).sum();
+        let error: f64 = intervals.iter().map(|interval| interval.3).sum();
+        if error <= tolerance.max(tolerance * value.abs()) {
+            return Ok((value, error));
+        }
+
+        let index = (0..intervals.len())
+            .max_by(|&i, &j| intervals[i].3.total_cmp(&intervals[j].3))
+            .unwrap_or(0);
+        let (lo, hi, ..) = intervals[index];
+        let mid = (lo + hi) / 2.0;
+        if intervals.len() >= MAX_SUBDIVISIONS || mid <= lo || mid >= hi {
+            return Err(format!("Integral did not converge, the estimated error is {:e}", error));
+        }
+
+        let (left, left_error) = gauss_kronrod(f, lo, mid)?;
+        let (right, right_error) = gauss_kronrod(f, mid, hi)?;
+        intervals[index] = (lo, mid, left, left_error);
+        intervals.push((mid, hi, right, right_error));
+    }
+}
+
+// integral of f over [a, b] to within the tolerance, returning the value and an error estimate.
+// infinite bounds are mapped onto finite intervals by a change of variable
+pub fn integrate(f: Function, a: f64, b: f64, tolerance: f64) -> Result<(f64, f64), String> {
+    if a.is_nan() || b.is_nan() {
+        return Err(String::from("Integration bounds must be numbers"));
+    }
+    if a == b {
+        return Ok((0.0, 0.0));
+    }
+    if a > b {
+        let (value, error) = integrate(f, b, a, tolerance)?;
+        return Ok((-value, error));
+    }
+
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => adaptive(f, a, b, tolerance),
+        (true, false) => {
+            // x = a + t / (1 - t)
+            let mut g = |t: f64| Ok(f(a + t / (1.0 - t))? / ((1.0 - t) * (1.0 - t)));
+            adaptive(&mut g, 0.0, 1.0, tolerance)
+        },
+        (false, true) => {
+            // x = b - (1 - t) / t
+            let mut g = |t: f64| Ok(f(b - (1.0 - t) / t)? / (t * t));
+            adaptive(&mut g, 0.0, 1.0, tolerance)
+        },
+        (false, false) => {
+            // x = t / (1 - t^2)
+            let mut g = |t: f64| {
+                let s = 1.0 - t * t;
+                Ok(f(t / s)? * (1.0 + t * t) / (s * s))
+            };
+            adaptive(&mut g, -1.0, 1.0, tolerance)
+        },
+    }
+}
+
+// most iterations of the root finders
+const MAX_ROOT_ITERATIONS: usize = 200;
+
+// number of pieces an interval is split into when looking for all of its roots
+const ROOT_SAMPLES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolveError {
+    NoSignChange{ a: f64, b: f64 },
+    NoBracket{ guess: f64 },
+    NotConverged{ iterations: usize, x: f64 },
+    NotFinite{ x: f64 },
+    Evaluation(String),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SolveError::NoSignChange{ a, b } => write!(f, "The function has the same sign at {} and {}", a, b),
+            SolveError::NoBracket{ guess } => write!(f, "Could not find a sign change near {}", guess),
+            SolveError::NotConverged{ iterations, x } => write!(f, "Did not converge after {} iterations, last estimate was {}", iterations, x),
+            SolveError::NotFinite{ x } => write!(f, "The function is not finite at {}", x),
+            SolveError::Evaluation(ref msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<String> for SolveError {
+    fn from(msg: String) -> SolveError {
+        SolveError::Evaluation(msg)
+    }
+}
+
+impl From<SolveError> for String {
+    fn from(error: SolveError) -> String {
+        error.to_string()
+    }
+}
+
+// a function of one variable for the root finders
+pub trait Objective {
+    fn value(&mut self, x: f64) -> Result<f64, String>;
+
+    // derivative at x, estimated with central differences unless it's known exactly
+    fn derivative(&mut self, x: f64) -> Result<f64, String> {
+        central_difference(self, x)
+    }
+
+    // size of the terms whose difference is the value at x, which rounding is relative to
+    fn scale(&mut self, x: f64) -> Result<f64, String> {
+        Ok(self.value(x)?.abs())
+    }
+}
+
+impl<F: FnMut(f64) -> Result<f64, String>> Objective for F {
+    fn value(&mut self, x: f64) -> Result<f64, String> {
+        self(x)
+    }
+}
+
+pub fn central_difference<F: Objective + ?Sized>(f: &mut F, x: f64) -> Result<f64, String> {
+    let h = 1e-6 * x.abs().max(1.0);
+    Ok((f.value(x + h)? - f.value(x - h)?) / (2.0 * h))
+}
+
+fn finite_value(f: &mut dyn Objective, x: f64) -> Result<f64, SolveError> {
+    let y = f.value(x)?;
+    if !y.is_finite() {
+        return Err(SolveError::NotFinite{ x });
+    }
+    Ok(y)
+}
+
+// a root is only accepted where the function rises above the rounding of its terms nearby, and
+// isn't smaller there than at the root. otherwise a zero may just be cancellation, like
+// x - (x + 1) once x is too large for the 1 to matter
+fn accept(f: &mut dyn Objective, x: f64, tolerance: f64) -> Result<f64, SolveError> {
+    let residual = f.value(x)?.abs();
+    let step = tolerance.sqrt() * x.abs().max(1.0);
+    for &near in &[x - step, x + step] {
+        let y = f.value(near)?.abs();
+        if y > f64::EPSILON * f.scale(near)?.max(1.0) && residual <= y {
+            return Ok(x);
+        }
+    }
+    Err(SolveError::NotConverged{ iterations: 0, x })
+}
+
+fn changes_sign(a: f64, b: f64) -> bool {
+    a.is_finite() && b.is_finite() && a.signum() != b.signum()
+}
+
+// Brent's method, which needs the function to change sign over [a, b]
+pub fn brent(f: &mut dyn Objective, a: f64, b: f64, tolerance: f64) -> Result<f64, SolveError> {
+    let (mut a, mut b) = (a, b);
+    let mut fa = finite_value(f, a)?;
+    let mut fb = finite_value(f, b)?;
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if fb == 0.0 {
+        return Ok(b);
+    }
+    if fa.signum() == fb.signum() {
+        return Err(SolveError::NoSignChange{ a, b });
+    }
+
+    let (mut c, mut fc) = (a, fa);
+    let mut d = b - a;
+    let mut e = d;
+    for _ in 0..MAX_ROOT_ITERATIONS {
+        if fb.signum() == fc.signum() {
+            c = a;
+            fc = fa;
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            a = b;
+            b = c;
+            c = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+
+        let tol = 2.0 * f64::EPSILON * b.abs() + tolerance / 2.0;
+        let m = (c - b) / 2.0;
+        if m.abs() <= tol || fb == 0.0 {
+            return Ok(b);
+        }
+
+        if e.abs() >= tol && fa.abs() > fb.abs() {
+            // inverse quadratic interpolation, or the secant method with only two points
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * m * s, 1.0 - s)
+            }
+            else {
+                let q = fa / fc;
+                let r = fb / fc;
+                (s * (2.0 * m * q * (q - r) - (b - a) * (r - 1.0)), (q - 1.0) * (r - 1.0) * (s - 1.0))
+            };
+            if p > 0.0 {
+                q = -q;
+            }
+            else {
+                p = -p;
+            }
+            if 2.0 * p < (3.0 * m * q - (tol * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            }
+            else {
+                d = m;
+                e = m;
+            }
+        }
+        else {
+            // bisection
+            d = m;
+            e = m;
+        }
+
+        a = b;
+        fa = fb;
+        b += if d.abs() > tol { d } else { tol.copysign(m) };
+        fb = finite_value(f, b)?;
+    }
+
+    Err(SolveError::NotConverged{ iterations: MAX_ROOT_ITERATIONS, x: b })
+}
+
+// Newton's method from an initial guess
+pub fn newton(f: &mut dyn Objective, guess: f64, tolerance: f64) -> Result<f64, SolveError> {
+    let mut x = guess;
+    for _ in 0..MAX_ROOT_ITERATIONS {
+        let y = finite_value(f, x)?;
+        if y == 0.0 {
+            return Ok(x);
+        }
+        let slope = f.derivative(x)?;
+        if slope == 0.0 || !slope.is_finite() {
+            return Err(SolveError::NotConverged{ iterations: 0, x });
+        }
+
+        let next = x - y / slope;
+        if !next.is_finite() {
+            return Err(SolveError::NotFinite{ x: next });
+        }
+        if (next - x).abs() <= tolerance * x.abs().max(1.0) {
+            return Ok(next);
+        }
+        x = next;
+    }
+
+    Err(SolveError::NotConverged{ iterations: MAX_ROOT_ITERATIONS, x })
+}
+
+// finds a root near a guess with Newton's method, falling back to searching outwards from the
+// guess for a sign change and using Brent's method on it
+pub fn root(f: &mut dyn Objective, guess: f64, tolerance: f64) -> Result<f64, SolveError> {
+    match newton(f, guess, tolerance) {
+        Ok(x) => if let Ok(x) = accept(f, x, tolerance) {
+            return Ok(x);
+        },
+        Err(SolveError::Evaluation(msg)) => return Err(SolveError::Evaluation(msg)),
+        Err(_) => { },
+    }
+
+    let start = f.value(guess)?;
+    let mut step = 0.1 * guess.abs().max(1.0);
+    let (mut left, mut f_left) = (guess, start);
+    let (mut right, mut f_right) = (guess, start);
+    for _ in 0..100 {
+        step *= 1.6;
+
+        let (next, f_next) = (guess - step, f.value(guess - step)?);
+        if changes_sign(f_next, f_left) {
+            let x = brent(f, next, left, tolerance)?;
+            return accept(f, x, tolerance);
+        }
+        left = next;
+        f_left = f_next;
+
+        let (next, f_next) = (guess + step, f.value(guess + step)?);
+        if changes_sign(f_right, f_next) {
+            let x = brent(f, right, next, tolerance)?;
+            return accept(f, x, tolerance);
+        }
+        right = next;
+        f_right = f_next;
+    }
+
+    Err(SolveError::NoBracket{ guess })
+}
+
+// all of the roots in [a, b] that can be found by sampling the function, including roots where
+// it touches zero without changing sign
+pub fn roots(f: &mut dyn Objective, a: f64, b: f64, tolerance: f64) -> Result<Vec<f64>, SolveError> {
+    if !(a.is_finite() && b.is_finite()) || a >= b {
+        return Err(SolveError::Evaluation(String::from("The interval must be finite with a < b")));
+    }
+
+    let step = (b - a) / ROOT_SAMPLES as f64;
+    let xs: Vec<f64> = (0..ROOT_SAMPLES + 1).map(|i| if i == ROOT_SAMPLES { b } else { a + step * i as f64 }).collect();
+    let mut ys = Vec::new();
+    for &x in &xs {
+        ys.push(f.value(x)?);
+    }
+
+    let mut output: Vec<f64> = Vec::new();
+    for i in 0..ROOT_SAMPLES + 1 {
+        if ys[i] == 0.0 {
+            output.push(xs[i]);
+        }
+        if i == ROOT_SAMPLES {
+            continue;
+        }
+        if ys[i] != 0.0 && ys[i + 1] != 0.0 && changes_sign(ys[i], ys[i + 1]) {
+            // a sign change across a pole isn't a root
+            match brent(f, xs[i], xs[i + 1], tolerance) {
+                Ok(x) => if f.value(x)?.abs() <= ys[i].abs().max(ys[i + 1].abs()) {
+                    output.push(x);
+                },
+                Err(SolveError::Evaluation(msg)) => return Err(SolveError::Evaluation(msg)),
+                Err(_) => { },
+            }
+        }
+        else if i > 0 && ys[i].is_finite() && ys[i].abs() < ys[i - 1].abs() && ys[i].abs() < ys[i + 1].abs() {
+            // a local minimum of |f| may be a root where the function only touches zero
+            if let Ok(x) = newton(f, xs[i], tolerance) {
+                if x >= xs[i - 1] && x <= xs[i + 1] && f.value(x)?.abs() <= tolerance {
+                    output.push(x);
+                }
+            }
+        }
+    }
+
+    output.sort_by(|x, y| x.total_cmp(y));
+    output.dedup_by(|x, y| (*x - *y).abs() <= 1e3 * tolerance * x.abs().max(1.0));
+    Ok(output)
+}
+
+// most steps the ODE solver will take before giving up
+const MAX_STEPS: usize = 100_000;
+
+// Dormand-Prince coefficients, the fifth order solution doubles as the last stage
+const DORMAND_PRINCE_NODES: [f64; 7] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+
+const DORMAND_PRINCE_STAGES: [[f64; 6]; 7] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+    [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+    [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0],
+];
+
+// difference between the fifth and fourth order weights
+const DORMAND_PRINCE_ERROR: [f64; 7] = [
+    35.0 / 384.0 - 5179.0 / 57600.0,
+    0.0,
+    500.0 / 1113.0 - 7571.0 / 16695.0,
+    125.0 / 192.0 - 393.0 / 640.0,
+    -2187.0 / 6784.0 + 92097.0 / 339200.0,
+    11.0 / 84.0 - 187.0 / 2100.0,
+    -1.0 / 40.0,
+];
+
+pub type System<'a> = &'a mut dyn FnMut(f64, &[f64]) -> Result<Vec<f64>, String>;
+
+fn derivatives(f: System, t: f64, y: &[f64]) -> Result<Vec<f64>, String> {
+    let dy = f(t, y)?;
+    if dy.len() != y.len() {
+        return Err(format!("Expected {} derivatives but found {}", y.len(), dy.len()));
+    }
+    if dy.iter().any(|d| !d.is_finite()) {
+        return Err(format!("Derivative is not finite at t = {}", t));
+    }
+    Ok(dy)
+}
+
+// adaptive Dormand-Prince 5(4), gives the state at each of the output times, which have to be
+// ordered from t0 on
+pub fn rk45(f: System, t0: f64, y0: &[f64], outputs: &[f64], tolerance: f64) -> Result<Vec<Vec<f64>>, String> {
+    let (mut t, mut y) = (t0, y0.to_vec());
+    let mut k = vec![derivatives(f, t, &y)?];
+    let mut h = outputs.last().map(|end| (end - t0) / 100.0).unwrap_or(0.0);
+    let mut steps = 0;
+
+    let mut states = Vec::new();
+    for &end in outputs {
+        while t != end {
+            // never step past the next output time
+            if (t + h - end) * h.signum() > 0.0 || h == 0.0 {
+                h = end - t;
+            }
+            if h.abs() <= 1e-14 * t.abs().max(1.0) {
+                return Err(format!("Step size became too small at t = {}", t));
+            }
+            steps += 1;
+            if steps > MAX_STEPS {
+                return Err(format!("ODE solver exceeded {} steps at t = {}", MAX_STEPS, t));
+            }
+
+            k.truncate(1);
+            let mut next = y.clone();
+            for stage in 1..7 {
+                next = (0..y.len())
+                    .map(|i| y[i] + h * (0..stage).map(|j| DORMAND_PRINCE_STAGES[stage][j] * k[j][i]).sum::<f64>())
+                    .collect();
+                k.push(derivatives(f, t + DORMAND_PRINCE_NODES[stage] * h, &next)?);
+            }
+
+            let error = (0..y.len()).map(|i| {
+                let estimate = h * (0..7).map(|j| DORMAND_PRINCE_ERROR[j] * k[j][i]).sum::<f64>();
+                estimate.abs() / (tolerance + tolerance * y[i].abs().max(next[i].abs()))
+            }).fold(0.0, f64::max);
+
+            if error <= 1.0 {
+                t = if (end - t - h).abs() <= 1e-14 * end.abs().max(1.0) { end } else { t + h };
+                y = next;
+                // the last stage is evaluated at the new point, so it starts the next step
+                let last = k.pop().unwrap_or_default();
+                k[0] = last;
+            }
+            h *= (0.9 * error.powf(-0.2)).clamp(0.2, 5.0);
+        }
+        states.push(y.clone());
+    }
+    Ok(states)
+}
+
+// Brent's minimization can't locate a minimum closer than this relative to its position
+const SQRT_EPSILON: f64 = 1.4901161193847656e-8;
+
+const GOLDEN_SECTION: f64 = 0.3819660112501051;
+
+// Brent's method for a minimum within [a, b], golden-section search sped up by parabolic steps
+pub fn minimize_scalar(f: Function, a: f64, b: f64, tolerance: f64, iterations: usize) -> Result<(f64, f64), String> {
+    let (mut a, mut b) = (a.min(b), a.max(b));
+    let mut evaluate = |x: f64| -> Result<f64, String> {
+        let y = f(x)?;
+        if y.is_nan() {
+            return Err(format!("Objective is not a number at {}", x));
+        }
+        Ok(y)
+    };
+
+    let mut x = a + GOLDEN_SECTION * (b - a);
+    let (mut w, mut v) = (x, x);
+    let mut fx = evaluate(x)?;
+    let (mut fw, mut fv) = (fx, fx);
+    let (mut d, mut e) = (0.0f64, 0.0f64);
+    for _ in 0..iterations {
+        let middle = 0.5 * (a + b);
+        let tolerance1 = SQRT_EPSILON * x.abs() + tolerance / 3.0;
+        let tolerance2 = 2.0 * tolerance1;
+        if (x - middle).abs() <= tolerance2 - 0.5 * (b - a) {
+            return Ok((x, fx));
+        }
+
+        // fit a parabola through x, w and v
+        let (mut p, mut q) = (0.0, 0.0);
+        let mut previous = 0.0;
+        if e.abs() > tolerance1 {
+            let r = (x - w) * (fx - fv);
+            q = (x - v) * (fx - fw);
+            p = (x - v) * q - (x - w) * r;
+            q = 2.0 * (q - r);
+            if q > 0.0 {
+                p = -p;
+            }
+            q = q.abs();
+            previous = e;
+            e = d;
+        }
+
+        if p.abs() < (0.5 * q * previous).abs() && p > q * (a - x) && p < q * (b - x) {
+            d = p / q;
+            let u = x + d;
+            if u - a < tolerance2 || b - u < tolerance2 {
+                d = if x < middle { tolerance1 } else { -tolerance1 };
+            }
+        }
+        else {
+            e = if x < middle { b - x } else { a - x };
+            d = GOLDEN_SECTION * e;
+        }
+
+        let u = if d.abs() >= tolerance1 { x + d } else { x + tolerance1.copysign(d) };
+        let fu = evaluate(u)?;
+        if fu <= fx {
+            if u < x { b = x } else { a = x }
+            v = w;
+            fv = fw;
+            w = x;
+            fw = fx;
+            x = u;
+            fx = fu;
+        }
+        else {
+            if u < x { a = u } else { b = u }
+            if fu <= fw || w == x {
+                v = w;
+                fv = fw;
+                w = u;
+                fw = fu;
+            }
+            else if fu <= fv || v == x || v == w {
+                v = u;
+                fv = fu;
+            }
+        }
+    }
+    Err(format!("Minimization did not converge in {} iterations", iterations))
+}
+
+pub type MultivariateFunction<'a> = &'a mut dyn FnMut(&[f64]) -> Result<f64, String>;
+
+// point on the line from the centroid through the worst vertex, negative t reflects it
+fn towards(centroid: &[f64], worst: &[f64], t: f64) -> Vec<f64> {
+    centroid.iter().zip(worst).map(|(c, w)| c + t * (w - c)).collect()
+}
+
+// Nelder-Mead downhill simplex starting around x0, converged once the function values at the
+// vertices agree within the tolerance and the simplex has shrunk to its square root
+pub fn nelder_mead(f: MultivariateFunction, x0: &[f64], tolerance: f64, iterations: usize) -> Result<(Vec<f64>, f64), String> {
+    let n = x0.len();
+    if n == 0 {
+        return Err(String::from("Expected at least one variable to minimize over"));
+    }
+    let mut evaluate = |x: &[f64]| -> Result<f64, String> {
+        let y = f(x)?;
+        if y.is_nan() {
+            let point: Vec<String> = x.iter().map(f64::to_string).collect();
+            return Err(format!("Objective is not a number at [{}]", point.join(", ")));
+        }
+        Ok(y)
+    };
+
+    let mut simplex = vec![(x0.to_vec(), evaluate(x0)?)];
+    for i in 0..n {
+        let mut x = x0.to_vec();
+        x[i] += if x[i] != 0.0 { 0.05 * x[i] } else { 0.00025 };
+        let y = evaluate(&x)?;
+        simplex.push((x, y));
+    }
+
+    for _ in 0..iterations {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0].1, simplex[n].1);
+        let size = simplex[1..].iter()
+            .map(|(x, _)| x.iter().zip(&simplex[0].0).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max))
+            .fold(0.0, f64::max);
+        let scale = 1.0 + simplex[0].0.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+        if (worst - best).abs() <= tolerance * (1.0 + best.abs()) && size <= tolerance.sqrt() * scale {
+            return Ok(simplex.swap_remove(0));
+        }
+
+        let centroid: Vec<f64> = (0..n).map(|j| simplex[..n].iter().map(|(x, _)| x[j]).sum::<f64>() / n as f64).collect();
+        let reflected = towards(&centroid, &simplex[n].0, -1.0);
+        let reflected_value = evaluate(&reflected)?;
+        if reflected_value < best {
+            let expanded = towards(&centroid, &simplex[n].0, -2.0);
+            let expanded_value = evaluate(&expanded)?;
+            simplex[n] = if expanded_value < reflected_value { (expanded, expanded_value) } else { (reflected, reflected_value) };
+        }
+        else if reflected_value < simplex[n - 1].1 {
+            simplex[n] = (reflected, reflected_value);
+        }
+        else {
+            let t = if reflected_value < worst { -0.5 } else { 0.5 };
+            let contracted = towards(&centroid, &simplex[n].0, t);
+            let contracted_value = evaluate(&contracted)?;
+            if contracted_value < reflected_value.min(worst) {
+                simplex[n] = (contracted, contracted_value);
+            }
+            else {
+                // shrink everything towards the best vertex
+                for i in 1..=n {
+                    let x = towards(&simplex[0].0, &simplex[i].0, 0.5);
+                    let y = evaluate(&x)?;
+                    simplex[i] = (x, y);
+                }
+            }
+        }
+    }
+    Err(format!("Minimization did not converge in {} iterations", iterations))
+}
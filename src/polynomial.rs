@@ -0,0 +1,361 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+use expr::Expr;
+use symbolic;
+use super::Operation;
+
+// highest degree a polynomial may reach through powers
+const MAX_DEGREE: usize = 10_000;
+
+// iterations of the Durand-Kerner method before giving up on the roots
+const MAX_ROOT_ITERATIONS: usize = 1000;
+
+// relative size below which coefficients left over from division count as zero
+const EPSILON: f64 = 1e-12;
+
+// just enough of the complex numbers for finding roots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Complex {
+        Complex{ re, im }
+    }
+
+    fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    fn powi(self, n: usize) -> Complex {
+        (0..n).fold(Complex::new(1.0, 0.0), |product, _| product * self)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        let denominator = other.re * other.re + other.im * other.im;
+        Complex::new((self.re * other.re + self.im * other.im) / denominator,
+                     (self.im * other.re - self.re * other.im) / denominator)
+    }
+}
+
+// polynomial in one variable, the coefficients go from the constant term up to the leading one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    coefficients: Vec<f64>,
+}
+
+impl Polynomial {
+    pub fn new(mut coefficients: Vec<f64>) -> Polynomial {
+        while coefficients.last() == Some(&0.0) {
+            coefficients.pop();
+        }
+        Polynomial{ coefficients }
+    }
+
+    pub fn constant(value: f64) -> Polynomial {
+        Polynomial::new(vec![value])
+    }
+
+    // from the coefficients in the order they're written, leading one first
+    pub fn from_descending(coefficients: &[f64]) -> Polynomial {
+        Polynomial::new(coefficients.iter().rev().cloned().collect())
+    }
+
+    pub fn descending(&self) -> Vec<f64> {
+        self.coefficients.iter().rev().cloned().collect()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    // the zero polynomial has no degree, it's reported as zero like the constants
+    pub fn degree(&self) -> usize {
+        self.coefficients.len().saturating_sub(1)
+    }
+
+    fn leading(&self) -> f64 {
+        self.coefficients.last().cloned().unwrap_or(0.0)
+    }
+
+    fn as_constant(&self) -> Option<f64> {
+        match self.coefficients.len() {
+            0 => Some(0.0),
+            1 => Some(self.coefficients[0]),
+            _ => None,
+        }
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        self.coefficients.iter().rev().fold(0.0, |result, &c| result * x + c)
+    }
+
+    fn eval_complex(&self, z: Complex) -> Complex {
+        self.coefficients.iter().rev().fold(Complex::new(0.0, 0.0), |result, &c| result * z + Complex::new(c, 0.0))
+    }
+
+    pub fn add(&self, other: &Polynomial) -> Polynomial {
+        let length = self.coefficients.len().max(other.coefficients.len());
+        let coefficient = |p: &Polynomial, i: usize| p.coefficients.get(i).cloned().unwrap_or(0.0);
+        Polynomial::new((0..length).map(|i| coefficient(self, i) + coefficient(other, i)).collect())
+    }
+
+    pub fn neg(&self) -> Polynomial {
+        self.scale(-1.0)
+    }
+
+    pub fn sub(&self, other: &Polynomial) -> Polynomial {
+        self.add(&other.neg())
+    }
+
+    pub fn scale(&self, factor: f64) -> Polynomial {
+        Polynomial::new(self.coefficients.iter().map(|c| c * factor).collect())
+    }
+
+    pub fn mul(&self, other: &Polynomial) -> Polynomial {
+        if self.is_zero() || other.is_zero() {
+            return Polynomial::new(Vec::new());
+        }
+        let mut coefficients = vec![0.0; self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in other.coefficients.iter().enumerate() {
+                coefficients[i + j] += a * b;
+            }
+        }
+        Polynomial::new(coefficients)
+    }
+
+    pub fn pow(&self, exponent: f64) -> Result<Polynomial, String> {
+        if exponent < 0.0 || exponent.fract() != 0.0 {
+            return Err(String::from("Polynomials can only be raised to non-negative integer powers"));
+        }
+        if self.degree() as f64 * exponent > MAX_DEGREE as f64 {
+            return Err(format!("Polynomial degree exceeds {}", MAX_DEGREE));
+        }
+        let mut result = Polynomial::constant(1.0);
+        for _ in 0..exponent as usize {
+            result = result.mul(self);
+        }
+        Ok(result)
+    }
+
+    // quotient and remainder of long division
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), String> {
+        if divisor.is_zero() {
+            return Err(String::from("Division by the zero polynomial"));
+        }
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![0.0; self.coefficients.len().saturating_sub(divisor.degree())];
+        let scale = self.coefficients.iter().fold(0.0f64, |max, c| max.max(c.abs()));
+        for i in (0..quotient.len()).rev() {
+            let factor = remainder[i + divisor.degree()] / divisor.leading();
+            quotient[i] = factor;
+            for (j, c) in divisor.coefficients.iter().enumerate() {
+                remainder[i + j] -= factor * c;
+            }
+            // the leading term cancels exactly, rounding may leave something tiny behind
+            remainder[i + divisor.degree()] = 0.0;
+        }
+        for c in remainder.iter_mut() {
+            if c.abs() <= EPSILON * scale {
+                *c = 0.0;
+            }
+        }
+        Ok((Polynomial::new(quotient), Polynomial::new(remainder)))
+    }
+
+    // greatest common divisor made monic, by the Euclidean algorithm
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.is_zero() {
+            let remainder = a.div_rem(&b).map(|(_, remainder)| remainder).unwrap_or_default();
+            a = b;
+            b = remainder;
+        }
+        match a.leading() {
+            leading if leading != 0.0 => a.scale(1.0 / leading),
+            _ => a,
+        }
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        Polynomial::new(self.coefficients.iter().enumerate().skip(1).map(|(i, c)| c * i as f64).collect())
+    }
+
+    // all complex roots at once by the Durand-Kerner method, sorted by real then imaginary part
+    pub fn roots(&self) -> Result<Vec<Complex>, String> {
+        if self.is_zero() {
+            return Err(String::from("Every number is a root of the zero polynomial"));
+        }
+        if self.coefficients.iter().any(|c| !c.is_finite()) {
+            return Err(String::from("Polynomial coefficients must be finite"));
+        }
+        let monic = self.scale(1.0 / self.leading());
+        if monic.coefficients.iter().any(|c| !c.is_finite()) {
+            return Err(String::from("Polynomial coefficients overflow when divided by the leading one"));
+        }
+        let n = monic.degree();
+
+        // spread the initial guesses around a circle as large as the roots can be
+        let radius = 1.0 + monic.coefficients[..n].iter().fold(0.0f64, |max, c| max.max(c.abs()));
+        let seed = Complex::new(0.4, 0.9);
+        let mut roots: Vec<Complex> = (0..n).map(|k| seed.powi(k) * Complex::new(radius / seed.norm().powi(k as i32), 0.0)).collect();
+
+        let mut converged = false;
+        for _ in 0..MAX_ROOT_ITERATIONS {
+            let mut change = 0.0f64;
+            for i in 0..n {
+                let mut denominator = Complex::new(1.0, 0.0);
+                for j in 0..n {
+                    if i != j {
+                        denominator = denominator * (roots[i] - roots[j]);
+                    }
+                }
+                let step = monic.eval_complex(roots[i]) / denominator;
+                if step.re.is_finite() && step.im.is_finite() {
+                    roots[i] = roots[i] - step;
+                    change = change.max(step.norm() / roots[i].norm().max(1.0));
+                }
+            }
+            if change < EPSILON {
+                converged = true;
+                break;
+            }
+        }
+
+        // repeated roots only converge linearly, so they're accepted once the residual is small
+        let scale = monic.coefficients.iter().fold(0.0f64, |sum, c| sum + c.abs());
+        if !converged && roots.iter().any(|&z| monic.eval_complex(z).norm() > 1e-6 * scale * z.norm().max(1.0).powi(n as i32)) {
+            return Err(String::from("Polynomial roots did not converge"));
+        }
+
+        // real and imaginary parts that are only rounding noise are dropped
+        for z in roots.iter_mut() {
+            let noise = 1e-10 * z.norm().max(1.0);
+            if z.re.abs() <= noise {
+                z.re = 0.0;
+            }
+            if z.im.abs() <= noise {
+                z.im = 0.0;
+            }
+        }
+        // a few Newton steps then polish away the error the simultaneous iteration leaves behind
+        let derivative = monic.derivative();
+        for z in roots.iter_mut() {
+            for _ in 0..3 {
+                let step = monic.eval_complex(*z) / derivative.eval_complex(*z);
+                let next = *z - step;
+                if !next.re.is_finite() || !next.im.is_finite() || monic.eval_complex(next).norm() >= monic.eval_complex(*z).norm() {
+                    break;
+                }
+                *z = next;
+            }
+        }
+
+        if roots.iter().any(|z| !z.re.is_finite() || !z.im.is_finite()) {
+            return Err(String::from("Polynomial roots are not finite"));
+        }
+        roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        Ok(roots)
+    }
+
+    // converts an expression in one variable, the parts that don't depend on it are handed to constant
+    pub fn from_expr(expr: &Expr, variable: &str, constant: &mut dyn FnMut(&Expr) -> Result<f64, String>) -> Result<Polynomial, String> {
+        if !symbolic::depends_on(expr, variable) {
+            return Ok(Polynomial::constant(constant(expr)?));
+        }
+
+        let args = match *expr {
+            Expr::Variable(..) => return Ok(Polynomial::new(vec![0.0, 1.0])),
+            Expr::Call(_, ref args) => args,
+            _ => return Err(format!("Cannot make a polynomial in {} of {}", variable, expr)),
+        };
+        let mut convert = |arg: &Expr| Polynomial::from_expr(arg, variable, constant);
+
+        match *expr {
+            Expr::Call(Operation::Identity, _) => convert(&args[0]),
+            Expr::Call(Operation::Negation, _) => Ok(convert(&args[0])?.neg()),
+            Expr::Call(Operation::Addition, _) => Ok(convert(&args[0])?.add(&convert(&args[1])?)),
+            Expr::Call(Operation::Subtraction, _) => Ok(convert(&args[0])?.sub(&convert(&args[1])?)),
+            Expr::Call(Operation::Multiplication, _) => Ok(convert(&args[0])?.mul(&convert(&args[1])?)),
+            Expr::Call(Operation::Division, _) if !symbolic::depends_on(&args[1], variable) => {
+                let dividend = convert(&args[0])?;
+                Ok(dividend.scale(1.0 / constant(&args[1])?))
+            },
+            Expr::Call(Operation::Power, _) if !symbolic::depends_on(&args[1], variable) => {
+                let base = convert(&args[0])?;
+                base.pow(constant(&args[1])?)
+            },
+            _ => Err(format!("Cannot make a polynomial in {} of {}", variable, expr)),
+        }
+    }
+}
+
+impl Default for Polynomial {
+    fn default() -> Polynomial {
+        Polynomial::new(Vec::new())
+    }
+}
+
+// written out like an expression, as in 2x^2 - 3x + 1
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(value) = self.as_constant() {
+            return write!(f, "{}", value);
+        }
+
+        let mut first = true;
+        for (degree, &c) in self.coefficients.iter().enumerate().rev() {
+            if c == 0.0 {
+                continue;
+            }
+            if first {
+                if c < 0.0 {
+                    write!(f, "-")?;
+                }
+            }
+            else {
+                write!(f, " {} ", if c < 0.0 { "-" } else { "+" })?;
+            }
+            first = false;
+
+            let magnitude = c.abs();
+            if magnitude != 1.0 || degree == 0 {
+                write!(f, "{}", magnitude)?;
+            }
+            match degree {
+                0 => { },
+                1 => write!(f, "x")?,
+                _ => write!(f, "x^{}", degree)?,
+            }
+        }
+        Ok(())
+    }
+}
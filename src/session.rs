@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::vec::IntoIter;
+
+use expr::Expr;
+use polynomial::Polynomial;
+use value::{Lambda, Value};
+use word::{Overflow, Word, WordMode};
+use super::Calculator;
+
+// a session is saved as text with one variable, formula, const declaration or result per line:
+//
+//     # calc-rust session
+//     version 1
+//     var k = 5
+//     var f = fn [ t ] "t ^ 3 + k" { k = 5 }
+//     formula y = "k + 1"
+//     const k 1
+//     result [ 1 2.5 nan:7ff8000000000000 ]
+//
+// numbers are written in their shortest exact form and NaN with its bits, so every number reads
+// back to the same bits
+pub const VERSION: u32 = 1;
+
+const HEADER: &str = "# calc-rust session";
+
+fn write_number(x: f64) -> String {
+    if x.is_nan() { format!("nan:{:016x}", x.to_bits()) } else { format!("{:?}", x) }
+}
+
+// items between brackets, which are separated by spaces like everything else
+fn group(open: &str, items: Vec<String>, close: &str) -> String {
+    let mut words = vec![String::from(open)];
+    words.extend(items);
+    words.push(String::from(close));
+    words.join(" ")
+}
+
+fn write_value(value: &Value) -> String {
+    match *value {
+        Value::Number(x) => write_number(x),
+        Value::Word(word) => {
+            let overflow = if word.mode.overflow == Overflow::Check { "check" } else { "wrap" };
+            format!("{}:{}:{:x}", word.mode, overflow, word.bits)
+        },
+        Value::List(ref items) => group("[", items.iter().map(write_value).collect(), "]"),
+        Value::Polynomial(ref polynomial) => format!("poly {}", group("[", polynomial.descending().into_iter().map(write_number).collect(), "]")),
+        Value::Function(ref lambda) => {
+            let captures = lambda.captures.iter().map(|(name, value)| format!("{} = {}", name, write_value(value))).collect();
+            format!("fn {} \"{}\" {}", group("[", lambda.parameters.clone(), "]"), lambda.body, group("{", captures, "}"))
+        },
+    }
+}
+
+pub fn save(calculator: &Calculator) -> String {
+    let mut lines = vec![String::from(HEADER), format!("version {}", VERSION)];
+    let mut names: Vec<&String> = calculator.variables.keys().collect();
+    names.sort();
+    for name in names {
+        lines.push(format!("var {} = {}", name, write_value(&calculator.variables[name])));
+    }
+    for (name, expr) in &calculator.formulas {
+        lines.push(format!("formula {} = \"{}\"", name, expr));
+    }
+    for (name, result) in &calculator.read_only {
+        lines.push(format!("const {} {}", name, result));
+    }
+    for result in &calculator.results {
+        lines.push(format!("result {}", write_value(result)));
+    }
+    lines.join("\n") + "\n"
+}
+
+// splits a line at spaces, keeping quoted expressions whole along with their quotes
+fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let end = match rest.strip_prefix('"') {
+            Some(quoted) => quoted.find('"').ok_or_else(|| String::from("Missing closing quote"))? + 2,
+            None => rest.find(char::is_whitespace).unwrap_or(rest.len()),
+        };
+        words.push(rest[..end].to_string());
+        rest = rest[end..].trim_start();
+    }
+    Ok(words)
+}
+
+fn read_number(word: &str) -> Result<f64, String> {
+    match word.strip_prefix("nan:") {
+        Some(bits) => u64::from_str_radix(bits, 16).map(f64::from_bits).map_err(|_| format!("Bad NaN bits '{}'", bits)),
+        None => word.parse().map_err(|_| format!("Expected a number but found '{}'", word)),
+    }
+}
+
+// a word as its mode and bits, like i32:wrap:ffffffff
+fn read_word(word: &str) -> Result<Word, String> {
+    let parts: Vec<&str> = word.split(':').collect();
+    let bad = || format!("Bad integer '{}'", word);
+    if parts.len() != 3 || !(parts[0].starts_with('i') || parts[0].starts_with('u')) {
+        return Err(bad());
+    }
+    let bits = parts[0][1..].parse().map_err(|_| bad())?;
+    let overflow = match parts[1] {
+        "wrap" => Overflow::Wrap,
+        "check" => Overflow::Check,
+        _ => return Err(bad()),
+    };
+    let mode = WordMode::new(bits, parts[0].starts_with('i'), overflow)?;
+    let value = u128::from_str_radix(parts[2], 16).map_err(|_| bad())?;
+    if bits < 128 && value >> bits != 0 {
+        return Err(format!("{} doesn't fit in {}", value, mode));
+    }
+    Ok(Word{ bits: value, mode })
+}
+
+// reads the words of one line
+struct Reader<'a> {
+    calculator: &'a Calculator,
+    words: Peekable<IntoIter<String>>,
+}
+
+impl<'a> Reader<'a> {
+    fn next(&mut self) -> Result<String, String> {
+        self.words.next().ok_or_else(|| String::from("Unexpected end of line"))
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            ref word if word == expected => Ok(()),
+            word => Err(format!("Expected '{}' but found '{}'", expected, word)),
+        }
+    }
+
+    // whether the next word closes a group, which is then skipped
+    fn closes(&mut self, close: &str) -> bool {
+        let closes = self.words.peek().is_some_and(|word| word == close);
+        if closes {
+            self.words.next();
+        }
+        closes
+    }
+
+    fn end(&mut self) -> Result<(), String> {
+        match self.words.next() {
+            Some(word) => Err(format!("Unexpected '{}'", word)),
+            None => Ok(()),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let name = self.next()?;
+        self.calculator.check_variable_name(&name)?;
+        Ok(name)
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let word = self.next()?;
+        match word.strip_prefix('"').and_then(|word| word.strip_suffix('"')) {
+            Some(input) => self.calculator.read(input),
+            None => Err(format!("Expected a quoted expression but found '{}'", word)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        let word = self.next()?;
+        match word.as_str() {
+            "[" => {
+                let mut items = Vec::new();
+                while !self.closes("]") {
+                    items.push(self.value()?);
+                }
+                Ok(Value::List(items))
+            },
+            "poly" => {
+                self.expect("[")?;
+                let mut coefficients = Vec::new();
+                while !self.closes("]") {
+                    coefficients.push(read_number(&self.next()?)?);
+                }
+                Ok(Value::Polynomial(Polynomial::from_descending(&coefficients)))
+            },
+            "fn" => {
+                self.expect("[")?;
+                let mut parameters = Vec::new();
+                while !self.closes("]") {
+                    parameters.push(self.name()?);
+                }
+                let body = self.expression()?;
+                self.expect("{")?;
+                let mut captures = Vec::new();
+                while !self.closes("}") {
+                    let name = self.name()?;
+                    self.expect("=")?;
+                    captures.push((name, self.value()?));
+                }
+                Ok(Value::Function(Rc::new(Lambda{ parameters, body, captures })))
+            },
+            _ if word.contains(':') && !word.starts_with("nan:") => Ok(Value::Word(read_word(&word)?)),
+            _ => Ok(Value::Number(read_number(&word)?)),
+        }
+    }
+}
+
+// replaces the variables, formulas, const declarations and results of the calculator with those
+// of a saved session, leaving them as they were if it can't be read
+pub fn load(calculator: &mut Calculator, text: &str) -> Result<(), String> {
+    let mut lines = text.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+    match lines.next().map(|(_, line)| line.trim()) {
+        Some(line) if line == format!("version {}", VERSION) => { },
+        Some(line) if line.starts_with("version ") => return Err(format!("Unsupported session {}, expected version {}", line, VERSION)),
+        _ => return Err(String::from("Not a session, it doesn't start with a version")),
+    }
+
+    let mut variables = HashMap::new();
+    let mut formulas = Vec::new();
+    let mut read_only = Vec::new();
+    let mut results = Vec::new();
+    for (number, line) in lines {
+        let mut read = || -> Result<(), String> {
+            let mut reader = Reader{ calculator, words: split(line)?.into_iter().peekable() };
+            match reader.next()?.as_str() {
+                "var" => {
+                    let name = reader.name()?;
+                    reader.expect("=")?;
+                    variables.insert(name, reader.value()?);
+                },
+                "formula" => {
+                    let name = reader.name()?;
+                    reader.expect("=")?;
+                    formulas.push((name, reader.expression()?));
+                },
+                "const" => {
+                    let name = reader.name()?;
+                    let result = reader.next()?;
+                    read_only.push((name, result.parse().map_err(|_| format!("Expected a result number but found '{}'", result))?));
+                },
+                "result" => results.push(reader.value()?),
+                entry => return Err(format!("Unknown entry '{}'", entry)),
+            }
+            reader.end()
+        };
+        read().map_err(|msg| format!("Line {} of the session: {}", number + 1, msg))?;
+    }
+
+    calculator.variables = variables;
+    calculator.formulas = formulas;
+    calculator.read_only = read_only;
+    calculator.results = results;
+    Ok(())
+}
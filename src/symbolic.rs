@@ -0,0 +1,563 @@
+use std::cmp::Ordering;
+use std::f64::consts;
+
+use expr::Expr;
+use value::Value;
+use super::Operation;
+
+// deepest nesting of lambda calls that will be inlined
+const MAX_INLINE_DEPTH: usize = 64;
+
+fn number(value: f64) -> Expr {
+    Expr::Number(value)
+}
+
+fn is_number(expr: &Expr, value: f64) -> bool {
+    match *expr {
+        Expr::Number(n) => n == value,
+        _ => false,
+    }
+}
+
+fn call(operation: Operation, arg: Expr) -> Expr {
+    Expr::Call(operation, vec![arg])
+}
+
+fn binary(operation: Operation, a: Expr, b: Expr) -> Expr {
+    Expr::Call(operation, vec![a, b])
+}
+
+fn neg(a: Expr) -> Expr {
+    match a {
+        Expr::Number(n) => number(-n),
+        Expr::Call(Operation::Negation, mut args) => args.remove(0),
+        _ => call(Operation::Negation, a),
+    }
+}
+
+fn add(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (&Expr::Number(x), &Expr::Number(y)) => number(x + y),
+        _ if is_number(&a, 0.0) => b,
+        _ if is_number(&b, 0.0) => a,
+        _ => binary(Operation::Addition, a, b),
+    }
+}
+
+fn sub(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (&Expr::Number(x), &Expr::Number(y)) => number(x - y),
+        _ if is_number(&a, 0.0) => neg(b),
+        _ if is_number(&b, 0.0) => a,
+        _ => binary(Operation::Subtraction, a, b),
+    }
+}
+
+fn mul(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (&Expr::Number(x), &Expr::Number(y)) => number(x * y),
+        _ if is_number(&a, 0.0) || is_number(&b, 0.0) => number(0.0),
+        _ if is_number(&a, 1.0) => b,
+        _ if is_number(&b, 1.0) => a,
+        _ if is_number(&a, -1.0) => neg(b),
+        _ if is_number(&b, -1.0) => neg(a),
+        _ => binary(Operation::Multiplication, a, b),
+    }
+}
+
+fn div(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (&Expr::Number(x), &Expr::Number(y)) if y != 0.0 => number(x / y),
+        _ if is_number(&a, 0.0) => number(0.0),
+        _ if is_number(&b, 1.0) => a,
+        _ => binary(Operation::Division, a, b),
+    }
+}
+
+fn pow(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (&Expr::Number(x), &Expr::Number(y)) => number(x.powf(y)),
+        _ if is_number(&b, 0.0) => number(1.0),
+        _ if is_number(&b, 1.0) => a,
+        _ => binary(Operation::Power, a, b),
+    }
+}
+
+fn is_pure(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Number(..) | Expr::Integer(..) | Expr::Variable(..) => true,
+        Expr::List(ref items) => items.iter().all(is_pure),
+        Expr::Call(operation, ref args) => operation.is_pure() && args.iter().all(is_pure),
+    }
+}
+
+pub fn depends_on(expr: &Expr, variable: &str) -> bool {
+    let mut free = Vec::new();
+    expr.free_variables(&mut Vec::new(), &mut free);
+    free.iter().any(|name| name == variable)
+}
+
+// replaces the free occurrences of variables with expressions, all at once
+pub fn substitute(expr: &Expr, bindings: &[(String, Expr)]) -> Expr {
+    match *expr {
+        Expr::Number(..) | Expr::Integer(..) => expr.clone(),
+        Expr::Variable(ref name) => match bindings.iter().find(|(variable, ..)| variable == name) {
+            Some((_, replacement)) => replacement.clone(),
+            None => expr.clone(),
+        },
+        Expr::List(ref items) => Expr::List(items.iter().map(|item| substitute(item, bindings)).collect()),
+        Expr::Call(Operation::Lambda, ref args) => {
+            // parameters shadow the variables being replaced
+            let parameters = args[0].parameter_names().unwrap_or_default();
+            let inner: Vec<(String, Expr)> = bindings.iter()
+                .filter(|(variable, ..)| !parameters.contains(variable))
+                .cloned().collect();
+            binary(Operation::Lambda, args[0].clone(), substitute(&args[1], &inner))
+        },
+        Expr::Call(operation, ref args) => {
+            let binding = operation.binding();
+            let args = args.iter().enumerate().map(|(i, arg)| match binding {
+                Some((variable, _)) if variable == i => arg.clone(),
+                Some((variable, body)) if body == i => {
+                    let bound = args.get(variable).and_then(|arg| arg.parameter_names().ok()).unwrap_or_default();
+                    let inner: Vec<(String, Expr)> = bindings.iter()
+                        .filter(|(name, ..)| !bound.contains(name))
+                        .cloned().collect();
+                    substitute(arg, &inner)
+                },
+                _ => substitute(arg, bindings),
+            });
+            Expr::Call(operation, args.collect())
+        },
+    }
+}
+
+// replaces calls to lambdas with their bodies so that they can be manipulated symbolically
+pub fn inline(expr: &Expr, resolve: &dyn Fn(&str) -> Option<Value>) -> Result<Expr, String> {
+    inline_nested(expr, resolve, 0)
+}
+
+fn inline_nested(expr: &Expr, resolve: &dyn Fn(&str) -> Option<Value>, depth: usize) -> Result<Expr, String> {
+    match *expr {
+        Expr::Number(..) | Expr::Integer(..) | Expr::Variable(..) => Ok(expr.clone()),
+        Expr::List(ref items) => {
+            let mut output = Vec::new();
+            for item in items {
+                output.push(inline_nested(item, resolve, depth)?);
+            }
+            Ok(Expr::List(output))
+        },
+        Expr::Call(operation, ref args) => {
+            let mut output = Vec::new();
+            for arg in args {
+                output.push(inline_nested(arg, resolve, depth)?);
+            }
+            if let Operation::Call = operation { } else {
+                return Ok(Expr::Call(operation, output));
+            }
+
+            if depth == MAX_INLINE_DEPTH {
+                return Err(String::from("Function calls are nested too deeply"));
+            }
+            let lambda = match args[0].as_variable().and_then(resolve) {
+                Some(Value::Function(lambda)) => lambda,
+                _ => return Err(format!("Unknown function '{}'", args[0])),
+            };
+            if lambda.parameters.len() != args.len() - 1 {
+                return Err(format!("Expected {} arguments but found {}", lambda.parameters.len(), args.len() - 1));
+            }
+
+            // calls inside the body see the values captured by the lambda first
+            let captures = &lambda.captures;
+            let scope = |name: &str| captures.iter()
+                .find(|(captured, ..)| captured == name)
+                .map(|(_, value)| value.clone())
+                .or_else(|| resolve(name));
+            let body = inline_nested(&lambda.body, &scope, depth + 1)?;
+
+            let mut bindings: Vec<(String, Expr)> = lambda.parameters.iter().cloned().zip(output.drain(1..)).collect();
+            for (name, value) in captures {
+                if let Value::Number(n) = *value {
+                    if !lambda.parameters.contains(name) {
+                        bindings.push((name.clone(), number(n)));
+                    }
+                }
+            }
+            Ok(substitute(&body, &bindings))
+        },
+    }
+}
+
+// an expression times a constant factor, which is left out when it's one
+fn scale(expr: Expr, factor: f64) -> Expr {
+    if factor == 1.0 { expr } else { mul(number(factor), expr) }
+}
+
+// derivative of an expression with respect to a variable, other variables are held constant. the
+// unit is the size in radians of the angles trigonometric functions work in
+pub fn differentiate(expr: &Expr, variable: &str, unit: f64) -> Result<Expr, String> {
+    // derivatives of derivatives are expressions of their own variable
+    if let Expr::Call(Operation::Diff, ref args) = *expr {
+        if args.len() == 2 {
+            let inner = differentiate(&args[0], args[1].as_variable().unwrap_or_default(), unit)?;
+            return differentiate(&inner, variable, unit);
+        }
+    }
+
+    if !depends_on(expr, variable) {
+        return Ok(number(0.0));
+    }
+
+    let (operation, args) = match *expr {
+        Expr::Number(..) | Expr::Integer(..) => return Ok(number(0.0)),
+        Expr::Variable(..) => return Ok(number(1.0)),
+        Expr::List(ref items) => {
+            let mut output = Vec::new();
+            for item in items {
+                output.push(differentiate(item, variable, unit)?);
+            }
+            return Ok(Expr::List(output));
+        },
+        Expr::Call(operation, ref args) => (operation, args),
+    };
+
+    let a = || args[0].clone();
+    let b = || args[1].clone();
+    let da = || differentiate(&args[0], variable, unit);
+    let db = || differentiate(&args[1], variable, unit);
+
+    let derivative = match operation {
+        Operation::Identity => da()?,
+        Operation::Negation => neg(da()?),
+        Operation::Addition => add(da()?, db()?),
+        Operation::Subtraction => sub(da()?, db()?),
+        Operation::Multiplication => add(mul(da()?, b()), mul(a(), db()?)),
+        Operation::Division => div(sub(mul(da()?, b()), mul(a(), db()?)), pow(b(), number(2.0))),
+        Operation::Power => {
+            if !depends_on(&args[1], variable) {
+                // power rule
+                mul(mul(b(), pow(a(), sub(b(), number(1.0)))), da()?)
+            }
+            else if !depends_on(&args[0], variable) {
+                mul(mul(expr.clone(), call(Operation::Ln, a())), db()?)
+            }
+            else {
+                let inner = add(mul(db()?, call(Operation::Ln, a())), div(mul(b(), da()?), a()));
+                mul(expr.clone(), inner)
+            }
+        },
+        Operation::Sin => scale(mul(call(Operation::Cos, a()), da()?), unit),
+        Operation::Cos => scale(neg(mul(call(Operation::Sin, a()), da()?)), unit),
+        Operation::Tan => scale(div(da()?, pow(call(Operation::Cos, a()), number(2.0))), unit),
+        Operation::DegreeSign => scale(da()?, consts::PI / 180.0 / unit),
+        Operation::Abs => mul(call(Operation::Sign, a()), da()?),
+        Operation::Sqrt => div(da()?, mul(number(2.0), call(Operation::Sqrt, a()))),
+        Operation::Radians => mul(da()?, number(consts::PI / 180.0)),
+        Operation::Degrees => mul(da()?, number(180.0 / consts::PI)),
+        Operation::Ln => div(da()?, a()),
+        Operation::Log2 => div(da()?, mul(a(), call(Operation::Ln, number(2.0)))),
+        Operation::Log10 => div(da()?, mul(a(), call(Operation::Ln, number(10.0)))),
+        Operation::Exp => mul(call(Operation::Exp, a()), da()?),
+        Operation::Asin => scale(div(da()?, call(Operation::Sqrt, sub(number(1.0), pow(a(), number(2.0))))), 1.0 / unit),
+        Operation::Acos => scale(neg(div(da()?, call(Operation::Sqrt, sub(number(1.0), pow(a(), number(2.0)))))), 1.0 / unit),
+        Operation::Atan => scale(div(da()?, add(number(1.0), pow(a(), number(2.0)))), 1.0 / unit),
+        Operation::Atan2 => scale(div(sub(mul(b(), da()?), mul(a(), db()?)), add(pow(a(), number(2.0)), pow(b(), number(2.0)))), 1.0 / unit),
+        Operation::Sinh => mul(call(Operation::Cosh, a()), da()?),
+        Operation::Cosh => mul(call(Operation::Sinh, a()), da()?),
+        Operation::Tanh => div(da()?, pow(call(Operation::Cosh, a()), number(2.0))),
+        Operation::Asinh => div(da()?, call(Operation::Sqrt, add(pow(a(), number(2.0)), number(1.0)))),
+        Operation::Acosh => div(da()?, call(Operation::Sqrt, sub(pow(a(), number(2.0)), number(1.0)))),
+        Operation::Atanh => div(da()?, sub(number(1.0), pow(a(), number(2.0)))),
+        Operation::Frac => da()?,
+        Operation::Erf => mul(div(number(2.0), call(Operation::Sqrt, number(consts::PI))), mul(call(Operation::Exp, neg(pow(a(), number(2.0)))), da()?)),
+        Operation::Erfc => neg(mul(div(number(2.0), call(Operation::Sqrt, number(consts::PI))), mul(call(Operation::Exp, neg(pow(a(), number(2.0)))), da()?))),
+        Operation::Factorial => mul(mul(expr.clone(), call(Operation::Digamma, add(a(), number(1.0)))), da()?),
+        Operation::Gamma => mul(mul(expr.clone(), call(Operation::Digamma, a())), da()?),
+        Operation::LogGamma => mul(call(Operation::Digamma, a()), da()?),
+        Operation::Beta => {
+            let sum = call(Operation::Digamma, add(a(), b()));
+            add(mul(mul(expr.clone(), sub(call(Operation::Digamma, a()), sum.clone())), da()?),
+                mul(mul(expr.clone(), sub(call(Operation::Digamma, b()), sum)), db()?))
+        },
+        Operation::Hypot => div(add(mul(a(), da()?), mul(b(), db()?)), expr.clone()),
+        Operation::Cbrt => div(da()?, mul(number(3.0), pow(call(Operation::Cbrt, a()), number(2.0)))),
+        Operation::NthRoot if !depends_on(&args[1], variable) => div(mul(expr.clone(), da()?), mul(b(), a())),
+        Operation::Log => {
+            let base = args.get(1).cloned().unwrap_or(number(10.0));
+            differentiate(&div(call(Operation::Ln, a()), call(Operation::Ln, base)), variable, unit)?
+        },
+        Operation::Lerp => differentiate(&add(a(), mul(sub(b(), a()), args[2].clone())), variable, unit)?,
+
+        // whichever of the value and the bounds is chosen
+        Operation::Clamp => {
+            let (lower, upper) = (args[1].clone(), args[2].clone());
+            let inside = binary(Operation::And, binary(Operation::LessEqual, lower.clone(), a()), binary(Operation::LessEqual, a(), upper.clone()));
+            add(add(mul(inside, da()?), mul(binary(Operation::Less, a(), lower), db()?)),
+                mul(binary(Operation::Greater, a(), upper), differentiate(&args[2], variable, unit)?))
+        },
+
+        // piecewise constant functions
+        Operation::Floor | Operation::Ceil | Operation::Round | Operation::Trunc |
+        Operation::Sign | Operation::Not | Operation::Equality | Operation::Inequality |
+        Operation::Less | Operation::LessEqual | Operation::Greater | Operation::GreaterEqual |
+        Operation::And | Operation::Nand | Operation::Or | Operation::Nor => number(0.0),
+
+        // the comparisons select the derivative of whichever argument is chosen
+        Operation::Min => add(mul(binary(Operation::LessEqual, a(), b()), da()?),
+                              mul(binary(Operation::Greater, a(), b()), db()?)),
+        Operation::Max => add(mul(binary(Operation::GreaterEqual, a(), b()), da()?),
+                              mul(binary(Operation::Less, a(), b()), db()?)),
+
+        Operation::Sum => {
+            if depends_on(&args[1], variable) || depends_on(&args[2], variable) ||
+                args.get(4).is_some_and(|step| depends_on(step, variable)) {
+                return Err(String::from("Cannot differentiate a sum whose range depends on the variable"));
+            }
+            let mut output = args.clone();
+            output[3] = differentiate(&args[3], variable, unit)?;
+            Expr::Call(Operation::Sum, output)
+        },
+
+        _ => return Err(format!("Cannot differentiate '{}'", operation.name())),
+    };
+
+    Ok(derivative)
+}
+
+fn as_number(expr: &Expr) -> Option<f64> {
+    match *expr {
+        Expr::Number(value) => Some(value),
+        _ => None,
+    }
+}
+
+fn is_integer(expr: &Expr) -> bool {
+    as_number(expr).is_some_and(|value| value.fract() == 0.0)
+}
+
+// sum of numeric exponents, used to put higher order terms first
+fn degree(factors: &[(Expr, Expr)]) -> f64 {
+    factors.iter().filter_map(|(_, exponent)| as_number(exponent)).sum()
+}
+
+// canonical order of factors within a product, variables come before other factors
+fn factor_order(a: &(Expr, Expr), b: &(Expr, Expr)) -> Ordering {
+    let rank = |expr: &Expr| match *expr {
+        Expr::Variable(..) => 0,
+        _ => 1,
+    };
+    rank(&a.0).cmp(&rank(&b.0))
+        .then_with(|| a.0.to_string().cmp(&b.0.to_string()))
+        .then_with(|| a.1.to_string().cmp(&b.1.to_string()))
+}
+
+// canonical order of terms within a sum, higher degrees come first and constants last
+fn term_order(a: &(f64, Vec<(Expr, Expr)>), b: &(f64, Vec<(Expr, Expr)>)) -> Ordering {
+    a.1.is_empty().cmp(&b.1.is_empty())
+        .then_with(|| degree(&b.1).partial_cmp(&degree(&a.1)).unwrap_or(Ordering::Equal))
+        .then_with(|| build_product(1.0, a.1.clone()).to_string().cmp(&build_product(1.0, b.1.clone()).to_string()))
+}
+
+fn add_exponents(a: &Expr, b: &Expr) -> Expr {
+    match (as_number(a), as_number(b)) {
+        (Some(x), Some(y)) => number(x + y),
+        _ => {
+            let mut terms = Vec::new();
+            collect_terms(a, 1.0, &mut terms);
+            collect_terms(b, 1.0, &mut terms);
+            build_sum(terms)
+        },
+    }
+}
+
+fn scale_exponent(exponent: &Expr, scale: &Expr) -> Expr {
+    match (as_number(exponent), as_number(scale)) {
+        (Some(x), Some(y)) => number(x * y),
+        (_, Some(1.0)) => exponent.clone(),
+        _ => {
+            let mut coefficient = 1.0;
+            let mut factors = Vec::new();
+            collect_factors(exponent, &number(1.0), &mut coefficient, &mut factors);
+            collect_factors(scale, &number(1.0), &mut coefficient, &mut factors);
+            build_product(coefficient, factors)
+        },
+    }
+}
+
+// splits a product into a numeric coefficient and factors raised to exponents, combining factors
+// with the same base
+fn collect_factors(expr: &Expr, exponent: &Expr, coefficient: &mut f64, factors: &mut Vec<(Expr, Expr)>) {
+    // products can only be split up when raised to an integer power
+    let integer = is_integer(exponent);
+
+    match *expr {
+        Expr::Number(value) if integer => {
+            let power = value.powf(as_number(exponent).unwrap_or(1.0));
+            if power.is_finite() {
+                *coefficient *= power;
+                return;
+            }
+        },
+        Expr::Call(Operation::Multiplication, ref args) if integer => {
+            collect_factors(&args[0], exponent, coefficient, factors);
+            collect_factors(&args[1], exponent, coefficient, factors);
+            return;
+        },
+        Expr::Call(Operation::Division, ref args) if integer => {
+            collect_factors(&args[0], exponent, coefficient, factors);
+            collect_factors(&args[1], &scale_exponent(exponent, &number(-1.0)), coefficient, factors);
+            return;
+        },
+        Expr::Call(Operation::Negation, ref args) if integer => {
+            *coefficient *= (-1f64).powf(as_number(exponent).unwrap_or(1.0));
+            collect_factors(&args[0], exponent, coefficient, factors);
+            return;
+        },
+        Expr::Call(Operation::Identity, ref args) => {
+            collect_factors(&args[0], exponent, coefficient, factors);
+            return;
+        },
+        Expr::Call(Operation::Power, ref args) if integer => {
+            // (a^b)^c = a^(b*c) for integer c
+            collect_factors(&args[0], &scale_exponent(&args[1], exponent), coefficient, factors);
+            return;
+        },
+        _ => { },
+    }
+
+    match factors.iter().position(|(base, ..)| base == expr) {
+        Some(index) => factors[index].1 = add_exponents(&factors[index].1, exponent),
+        None => factors.push((expr.clone(), exponent.clone())),
+    }
+}
+
+fn build_product(coefficient: f64, mut factors: Vec<(Expr, Expr)>) -> Expr {
+    if coefficient == 0.0 {
+        return number(0.0);
+    }
+    factors.retain(|(_, exponent)| !is_number(exponent, 0.0));
+    factors.sort_by(factor_order);
+
+    let mut numerator: Vec<Expr> = Vec::new();
+    let mut denominator: Vec<Expr> = Vec::new();
+    for (base, exponent) in factors {
+        let (list, exponent) = match as_number(&exponent) {
+            Some(value) if value < 0.0 => (&mut denominator, number(-value)),
+            _ => (&mut numerator, exponent),
+        };
+        list.push(if is_number(&exponent, 1.0) { base } else { binary(Operation::Power, base, exponent) });
+    }
+
+    // the coefficient leads the numerator, a lone minus sign negates the first factor instead
+    if coefficient == -1.0 && !numerator.is_empty() {
+        numerator[0] = call(Operation::Negation, numerator[0].clone());
+    }
+    else if coefficient != 1.0 || numerator.is_empty() {
+        numerator.insert(0, number(coefficient));
+    }
+
+    let chain = |items: Vec<Expr>| items.into_iter().reduce(|a, b| binary(Operation::Multiplication, a, b));
+    let numerator = chain(numerator).unwrap_or_else(|| number(1.0));
+    match chain(denominator) {
+        Some(denominator) => binary(Operation::Division, numerator, denominator),
+        None => numerator,
+    }
+}
+
+// splits a sum into terms made of a numeric coefficient and factors, combining like terms
+fn collect_terms(expr: &Expr, sign: f64, terms: &mut Vec<(f64, Vec<(Expr, Expr)>)>) {
+    match *expr {
+        Expr::Call(Operation::Addition, ref args) => {
+            collect_terms(&args[0], sign, terms);
+            collect_terms(&args[1], sign, terms);
+        },
+        Expr::Call(Operation::Subtraction, ref args) => {
+            collect_terms(&args[0], sign, terms);
+            collect_terms(&args[1], -sign, terms);
+        },
+        Expr::Call(Operation::Negation, ref args) => collect_terms(&args[0], -sign, terms),
+        Expr::Call(Operation::Identity, ref args) => collect_terms(&args[0], sign, terms),
+        _ => {
+            let mut coefficient = sign;
+            let mut factors = Vec::new();
+            collect_factors(expr, &number(1.0), &mut coefficient, &mut factors);
+            factors.retain(|(_, exponent)| !is_number(exponent, 0.0));
+            factors.sort_by(factor_order);
+
+            match terms.iter().position(|(_, other)| *other == factors) {
+                Some(index) => terms[index].0 += coefficient,
+                None => terms.push((coefficient, factors)),
+            }
+        },
+    }
+}
+
+fn build_sum(mut terms: Vec<(f64, Vec<(Expr, Expr)>)>) -> Expr {
+    terms.retain(|(coefficient, _)| *coefficient != 0.0);
+    terms.sort_by(term_order);
+
+    // lead with a positive term if there is one, so 1 - x^2 doesn't become -x^2 + 1
+    if let Some(index) = terms.iter().position(|(coefficient, _)| *coefficient > 0.0) {
+        let term = terms.remove(index);
+        terms.insert(0, term);
+    }
+
+    let mut output: Option<Expr> = None;
+    for (coefficient, factors) in terms {
+        output = Some(match output {
+            None => build_product(coefficient, factors),
+            Some(sum) if coefficient < 0.0 => binary(Operation::Subtraction, sum, build_product(-coefficient, factors)),
+            Some(sum) => binary(Operation::Addition, sum, build_product(coefficient, factors)),
+        });
+    }
+    output.unwrap_or_else(|| number(0.0))
+}
+
+// algebraic simplification: constant folding, removing identities, combining like terms and
+// powers of the same base, and putting terms and factors in a canonical order. the fold callback
+// evaluates functions of constant arguments, which are only folded when the result is an integer
+// so that values like ln(2) stay exact
+pub fn simplify(expr: &Expr, fold: &dyn Fn(Operation, &[f64]) -> Option<f64>) -> Expr {
+    let (operation, args) = match *expr {
+        Expr::Number(..) | Expr::Integer(..) | Expr::Variable(..) => return expr.clone(),
+        Expr::List(ref items) => return Expr::List(items.iter().map(|item| simplify(item, fold)).collect()),
+        Expr::Call(operation, ref args) => (operation, args),
+    };
+
+    let binding = operation.binding();
+    let args: Vec<Expr> = args.iter().enumerate().map(|(i, arg)| match binding {
+        Some((variable, _)) if variable == i => arg.clone(),
+        _ => simplify(arg, fold),
+    }).collect();
+    let expr = Expr::Call(operation, args);
+
+    // random draws are different every time, so they're left just as they are
+    if !is_pure(&expr) {
+        return expr;
+    }
+
+    match operation {
+        Operation::Addition | Operation::Subtraction | Operation::Negation | Operation::Identity => {
+            let mut terms = Vec::new();
+            collect_terms(&expr, 1.0, &mut terms);
+            build_sum(terms)
+        },
+        Operation::Multiplication | Operation::Division | Operation::Power => {
+            let mut coefficient = 1.0;
+            let mut factors = Vec::new();
+            collect_factors(&expr, &number(1.0), &mut coefficient, &mut factors);
+            build_product(coefficient, factors)
+        },
+        Operation::Lambda | Operation::Assignment | Operation::Const | Operation::Call => expr,
+        _ => {
+            let params: Option<Vec<f64>> = match expr {
+                Expr::Call(_, ref args) => args.iter().map(as_number).collect(),
+                _ => None,
+            };
+            match params.and_then(|params| fold(operation, &params)) {
+                Some(value) if value.is_finite() && value.fract() == 0.0 => number(value),
+                _ => expr,
+            }
+        },
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+use std::rc::Rc;
+
+use expr::Expr;
+use polynomial::Polynomial;
+use word::Word;
+
+// an anonymous function along with the values it captured when it was created
+#[derive(Debug)]
+pub struct Lambda {
+    pub parameters: Vec<String>,
+    pub body: Expr,
+    pub captures: Vec<(String, Value)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    List(Vec<Value>),
+    Function(Rc<Lambda>),
+    Polynomial(Polynomial),
+    // an integer of programmer mode
+    Word(Word),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Number(..) => "number",
+            Value::List(..) => "list",
+            Value::Function(..) => "function",
+            Value::Polynomial(..) => "polynomial",
+            Value::Word(..) => "integer",
+        }
+    }
+
+    pub fn to_number(&self) -> Result<f64, String> {
+        match *self {
+            Value::Number(value) => Ok(value),
+            Value::Word(word) => Ok(word.to_f64()),
+            _ => Err(format!("Expected a number but found a {}", self.type_name())),
+        }
+    }
+
+    pub fn to_list(&self) -> Result<&[Value], String> {
+        match *self {
+            Value::List(ref items) => Ok(items),
+            _ => Err(format!("Expected a list but found a {}", self.type_name())),
+        }
+    }
+
+    pub fn to_numbers(&self) -> Result<Vec<f64>, String> {
+        self.to_list()?.iter().map(Value::to_number).collect()
+    }
+
+    // numbers are promoted to constant polynomials
+    pub fn to_polynomial(&self) -> Result<Polynomial, String> {
+        match *self {
+            Value::Number(value) => Ok(Polynomial::constant(value)),
+            Value::Word(word) => Ok(Polynomial::constant(word.to_f64())),
+            Value::Polynomial(ref polynomial) => Ok(polynomial.clone()),
+            _ => Err(format!("Expected a polynomial but found a {}", self.type_name())),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (&Value::Number(a), &Value::Number(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Polynomial(a), Value::Polynomial(b)) => a == b,
+            (Value::Word(a), Value::Word(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Number(value) => write!(f, "{}", value),
+            Value::List(ref items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Value::Function(ref lambda) => {
+                if lambda.parameters.len() == 1 {
+                    write!(f, "{} -> {}", lambda.parameters[0], lambda.body)
+                }
+                else {
+                    write!(f, "({}) -> {}", lambda.parameters.join(", "), lambda.body)
+                }
+            },
+            Value::Polynomial(ref polynomial) => write!(f, "{}", polynomial),
+            Value::Word(word) => write!(f, "{}", word),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+
+use super::Operation;
+
+// what happens when a result doesn't fit in the word
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Overflow {
+    Wrap,
+    Check,
+}
+
+// word size and signedness of programmer mode
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WordMode {
+    pub bits: u32,
+    pub signed: bool,
+    pub overflow: Overflow,
+}
+
+// an integer as the bit pattern of a word, which is only ever as wide as its mode
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Word {
+    pub bits: u128,
+    pub mode: WordMode,
+}
+
+// the operations that programmer mode carries out on words, anything else works on numbers
+pub fn is_supported(operation: Operation) -> bool {
+    matches!(operation,
+        Operation::Identity | Operation::Negation | Operation::Not | Operation::Addition | Operation::Subtraction |
+        Operation::Multiplication | Operation::Division | Operation::Modulus | Operation::Power |
+        Operation::Equality | Operation::Inequality | Operation::Less | Operation::LessEqual |
+        Operation::Greater | Operation::GreaterEqual | Operation::And | Operation::Nand | Operation::Or | Operation::Nor |
+        Operation::BitAnd | Operation::BitOr | Operation::BitXor | Operation::BitNot |
+        Operation::ShiftLeft | Operation::ShiftRight | Operation::RotateLeft | Operation::RotateRight |
+        Operation::Abs | Operation::Min | Operation::Max)
+}
+
+// a power by squaring, wrapped to 128 bits
+fn wrapping_pow(mut base: u128, mut exponent: u128) -> u128 {
+    let mut result: u128 = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
+}
+
+impl WordMode {
+    pub fn new(bits: u32, signed: bool, overflow: Overflow) -> Result<WordMode, String> {
+        if ![8, 16, 32, 64, 128].contains(&bits) {
+            return Err(format!("Word size must be 8, 16, 32, 64 or 128 bits, not {}", bits));
+        }
+        Ok(WordMode{ bits, signed, overflow })
+    }
+
+    fn mask(&self) -> u128 {
+        u128::MAX >> (128 - self.bits)
+    }
+
+    fn fits_signed(&self, value: i128) -> bool {
+        if self.signed {
+            let high = value >> (self.bits - 1);
+            high == 0 || high == -1
+        }
+        else {
+            value >= 0 && value as u128 <= self.mask()
+        }
+    }
+
+    fn fits_unsigned(&self, value: u128) -> bool {
+        value <= if self.signed { self.mask() >> 1 } else { self.mask() }
+    }
+
+    // keeps the low bits of a result, unless overflow is checked and the result didn't fit
+    fn fit(&self, bits: u128, overflowed: bool, describe: &dyn Fn() -> String) -> Result<Word, String> {
+        if overflowed && self.overflow == Overflow::Check {
+            return Err(format!("{} overflows {}", describe(), self));
+        }
+        Ok(Word{ bits: bits & self.mask(), mode: *self })
+    }
+
+    pub fn of_unsigned(&self, value: u128) -> Result<Word, String> {
+        self.fit(value, !self.fits_unsigned(value), &|| value.to_string())
+    }
+
+    pub fn of_signed(&self, value: i128) -> Result<Word, String> {
+        self.fit(value as u128, !self.fits_signed(value), &|| value.to_string())
+    }
+
+    pub fn of_bool(&self, value: bool) -> Word {
+        Word{ bits: value as u128, mode: *self }
+    }
+
+    pub fn of_f64(&self, value: f64) -> Result<Word, String> {
+        if value.fract() != 0.0 || !value.is_finite() {
+            return Err(format!("Expected an integer but found {}", value));
+        }
+        let limit = 2f64.powi(127);
+        if value < -limit || value >= 2.0 * limit {
+            return Err(format!("{} doesn't fit in 128 bits", value));
+        }
+        if value < 0.0 { self.of_signed(value as i128) } else { self.of_unsigned(value as u128) }
+    }
+
+    // a word computed in another mode, as the same integer in this one
+    pub fn convert(&self, word: Word) -> Result<Word, String> {
+        if word.mode == *self {
+            return Ok(word);
+        }
+        if word.is_negative() { self.of_signed(word.signed_value()) } else { self.of_unsigned(word.bits) }
+    }
+
+    // applies an operation to words of this mode
+    pub fn apply(&self, operation: Operation, args: &[Word]) -> Result<Word, String> {
+        let a = args[0];
+        let b = args.get(1).cloned().unwrap_or(a);
+        let (x, y) = (a.signed_value(), b.signed_value());
+        let describe = || format!("{} {} {}", a, operation.name(), b);
+
+        // the wrapped result is the same whatever the signedness, only the check differs
+        let checked = |result: Option<i128>, unsigned: Option<u128>| match self.signed {
+            true => result.is_none_or(|value| !self.fits_signed(value)),
+            false => unsigned.is_none_or(|value| !self.fits_unsigned(value)),
+        };
+
+        match operation {
+            Operation::Identity => Ok(a),
+            Operation::Negation => {
+                let overflowed = checked(x.checked_neg(), 0u128.checked_sub(a.bits));
+                self.fit(a.bits.wrapping_neg(), overflowed, &|| format!("-({})", a))
+            },
+            Operation::Addition => self.fit(a.bits.wrapping_add(b.bits), checked(x.checked_add(y), a.bits.checked_add(b.bits)), &describe),
+            Operation::Subtraction => self.fit(a.bits.wrapping_sub(b.bits), checked(x.checked_sub(y), a.bits.checked_sub(b.bits)), &describe),
+            Operation::Multiplication => self.fit(a.bits.wrapping_mul(b.bits), checked(x.checked_mul(y), a.bits.checked_mul(b.bits)), &describe),
+            Operation::Division | Operation::Modulus => {
+                if b.bits == 0 {
+                    return Err(String::from("Division by zero"));
+                }
+                let divide = matches!(operation, Operation::Division);
+                if self.signed {
+                    let result = if divide { x.checked_div(y) } else { x.checked_rem(y) };
+                    let wrapped = if divide { x.wrapping_div(y) } else { x.wrapping_rem(y) };
+                    self.fit(wrapped as u128, checked(result, None), &describe)
+                }
+                else {
+                    Ok(Word{ bits: if divide { a.bits / b.bits } else { a.bits % b.bits }, mode: *self })
+                }
+            },
+            Operation::Power => {
+                if b.is_negative() {
+                    return Err(format!("{} has a negative exponent", describe()));
+                }
+                let exponent = if b.bits <= u32::MAX as u128 { Some(b.bits as u32) } else { None };
+                // powers of 0, 1 and -1 never grow, however large the exponent
+                let grows = if self.signed { x.unsigned_abs() > 1 } else { a.bits > 1 };
+                let overflowed = grows && checked(exponent.and_then(|e| x.checked_pow(e)), exponent.and_then(|e| a.bits.checked_pow(e)));
+                self.fit(wrapping_pow(a.bits, b.bits), overflowed, &describe)
+            },
+            Operation::Abs if a.is_negative() => self.apply(Operation::Negation, &[a]),
+            Operation::Abs => Ok(a),
+            Operation::Min => Ok(if a.less(&b) { a } else { b }),
+            Operation::Max => Ok(if b.less(&a) { a } else { b }),
+
+            Operation::Equality => Ok(self.of_bool(a.bits == b.bits)),
+            Operation::Inequality => Ok(self.of_bool(a.bits != b.bits)),
+            Operation::Less => Ok(self.of_bool(a.less(&b))),
+            Operation::LessEqual => Ok(self.of_bool(!b.less(&a))),
+            Operation::Greater => Ok(self.of_bool(b.less(&a))),
+            Operation::GreaterEqual => Ok(self.of_bool(!a.less(&b))),
+            Operation::Not => Ok(self.of_bool(a.bits == 0)),
+            Operation::And => Ok(self.of_bool(a.bits != 0 && b.bits != 0)),
+            Operation::Nand => Ok(self.of_bool(!(a.bits != 0 && b.bits != 0))),
+            Operation::Or => Ok(self.of_bool(a.bits != 0 || b.bits != 0)),
+            Operation::Nor => Ok(self.of_bool(!(a.bits != 0 || b.bits != 0))),
+
+            // bits shifted or rotated out are dropped rather than treated as overflow
+            Operation::BitAnd => Ok(Word{ bits: a.bits & b.bits, mode: *self }),
+            Operation::BitOr => Ok(Word{ bits: a.bits | b.bits, mode: *self }),
+            Operation::BitXor => Ok(Word{ bits: a.bits ^ b.bits, mode: *self }),
+            Operation::BitNot => Ok(Word{ bits: !a.bits & self.mask(), mode: *self }),
+            Operation::ShiftLeft | Operation::ShiftRight | Operation::RotateLeft | Operation::RotateRight => {
+                if b.is_negative() {
+                    return Err(format!("{} shifts by a negative amount", describe()));
+                }
+                let width = self.bits as u128;
+                let bits = match operation {
+                    Operation::ShiftLeft if b.bits >= width => 0,
+                    Operation::ShiftLeft => a.bits << b.bits,
+                    // right shifts of signed words copy the sign bit
+                    Operation::ShiftRight if self.signed => (x >> b.bits.min(127)) as u128,
+                    Operation::ShiftRight if b.bits >= width => 0,
+                    Operation::ShiftRight => a.bits >> b.bits,
+                    _ => {
+                        let left = if let Operation::RotateLeft = operation { b.bits % width } else { (width - b.bits % width) % width };
+                        if left == 0 { a.bits } else { a.bits << left | a.bits >> (width - left) }
+                    },
+                };
+                Ok(Word{ bits: bits & self.mask(), mode: *self })
+            },
+            _ => Err(format!("{} is not available in programmer mode", operation.name())),
+        }
+    }
+}
+
+impl Word {
+    pub fn is_negative(&self) -> bool {
+        self.mode.signed && self.bits >> (self.mode.bits - 1) & 1 == 1
+    }
+
+    // the bits sign extended to 128, which is only the value for signed words
+    fn signed_value(&self) -> i128 {
+        let unused = 128 - self.mode.bits;
+        ((self.bits << unused) as i128) >> unused
+    }
+
+    fn less(&self, other: &Word) -> bool {
+        if self.mode.signed { self.signed_value() < other.signed_value() } else { self.bits < other.bits }
+    }
+
+    // the integer the word holds, which only 128-bit unsigned words can be too large for
+    pub fn to_i128(self) -> Option<i128> {
+        match self.bits {
+            _ if self.is_negative() => Some(self.signed_value()),
+            bits if bits <= i128::MAX as u128 => Some(bits as i128),
+            _ => None,
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        if self.is_negative() { self.signed_value() as f64 } else { self.bits as f64 }
+    }
+
+    pub fn hex(&self) -> String {
+        format!("0x{:0width$x}", self.bits, width = self.mode.bits as usize / 4)
+    }
+
+    // binary digits in groups of four
+    pub fn binary(&self) -> String {
+        let digits = format!("{:0width$b}", self.bits, width = self.mode.bits as usize);
+        let groups: Vec<&str> = (0..digits.len()).step_by(4).map(|i| &digits[i..i + 4]).collect();
+        format!("0b{}", groups.join("_"))
+    }
+}
+
+impl fmt::Display for WordMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", if self.signed { "i" } else { "u" }, self.bits)
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_negative() { write!(f, "{}", self.signed_value()) } else { write!(f, "{}", self.bits) }
+    }
+}